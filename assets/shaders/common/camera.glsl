@@ -0,0 +1,7 @@
+// 由CameraUniform每帧更新，见 geom/camera.rs
+layout (std140) uniform Camera {
+    mat4 proj;
+    mat4 view;
+    mat4 vp_proj;
+    vec4 viewPos;
+};
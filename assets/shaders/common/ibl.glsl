@@ -0,0 +1,40 @@
+// 基于图像的环境光照(IBL)，预计算结果由 render_gl/ibl.rs 绑定
+// 使用前需 #import，并在程序上调用 Environment::bind_program
+
+layout (std140) uniform Environment {
+    // x: 强度，y: 预过滤贴图的最大mip层级，z: 开启时为1
+    vec4 environmentParams;
+};
+
+uniform samplerCube irradianceMap;
+uniform samplerCube prefilterMap;
+uniform sampler2D brdfLut;
+
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness)
+{
+    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+// 漫反射取辐照度图，镜面反射取预过滤贴图与BRDF查找表(split sum)
+vec3 ambientLighting(vec3 norm, vec3 view_dirction, vec3 albedo, float roughness)
+{
+    // 非金属材质的基础反射率
+    vec3 F0 = vec3(0.04);
+    float NdotV = max(dot(norm, view_dirction), 0.0);
+    vec3 F = fresnelSchlickRoughness(NdotV, F0, roughness);
+
+    vec3 diffuse = (1.0 - F) * texture(irradianceMap, norm).rgb * albedo;
+
+    vec3 reflect_direction = reflect(-view_dirction, norm);
+    vec3 prefiltered = textureLod(prefilterMap, reflect_direction, roughness * environmentParams.y).rgb;
+    vec2 brdf = texture(brdfLut, vec2(NdotV, roughness)).rg;
+    vec3 specular = prefiltered * (F * brdf.x + brdf.y);
+
+    return (diffuse + specular) * environmentParams.x * environmentParams.z;
+}
+
+// 开启IBL时光源的环境光分量不再生效，作为 calcLighting 的 ambient_weight
+float lightAmbientWeight()
+{
+    return 1.0 - environmentParams.z;
+}
@@ -0,0 +1,108 @@
+// 多光源Phong光照，光源由 geom/light.rs 的 LightsUniform 上传
+// 使用前需 #import，光源数组长度与 geom/light.rs 中的常量保持一致
+// std140布局，字段顺序需与 geom/light.rs 中的 *Std140 结构体一致
+
+#define MAX_DIRECT_LIGHTS 4
+#define MAX_POINT_LIGHTS 8
+#define MAX_SPOT_LIGHTS 4
+
+struct DirectLight {
+    vec3 ambient;
+    bool isOn;
+    vec3 diffuse;
+    vec3 specular;
+
+    vec3 direction;
+};
+
+struct PointLight {
+    vec3 ambient;
+    bool isOn;
+    vec3 diffuse;
+    vec3 specular;
+
+    vec3 position;
+    float constant;
+    float linear;
+    float quadratic;
+};
+
+struct SpotLight {
+    vec3 ambient;
+    bool isOn;
+    vec3 diffuse;
+    vec3 specular;
+
+    vec3 position;
+    float constant;
+    vec3 direction;
+    float linear;
+    float quadratic;
+    // 内外切光角的余弦值
+    float cutOff;
+    float outerCutOff;
+};
+
+layout (std140) uniform Lights {
+    DirectLight dirLights[MAX_DIRECT_LIGHTS];
+    PointLight pointLights[MAX_POINT_LIGHTS];
+    SpotLight spotLights[MAX_SPOT_LIGHTS];
+    int dirLightCount;
+    int pointLightCount;
+    int spotLightCount;
+};
+
+const float shininess = 256.0;
+
+// 单个光源的 环境光+漫反射+镜面反射，light_direction 由片元指向光源
+vec3 phong(vec3 ambient, vec3 diffuse, vec3 specular, vec3 light_direction, vec3 norm, vec3 view_dirction)
+{
+    float diff = max(dot(norm, light_direction), 0.0);
+    vec3 reflect_direction = reflect(-light_direction, norm);
+    float spec = pow(max(dot(view_dirction, reflect_direction), 0.0), shininess);
+    return ambient + diff * diffuse + spec * specular;
+}
+
+float attenuation(vec3 position, vec3 world_coord, float constant, float linear, float quadratic)
+{
+    float distance = length(position - world_coord);
+    return 1.0 / (constant + linear * distance + quadratic * distance * distance);
+}
+
+// 所有光源的光照之和，norm与view_dirction需已归一化
+// ambient_weight 缩放各光源的环境光分量
+vec3 calcLighting(vec3 norm, vec3 view_dirction, vec3 world_coord, float ambient_weight)
+{
+    vec3 lighting = vec3(0.0);
+
+    for (int i = 0; i < dirLightCount; i++) {
+        DirectLight light = dirLights[i];
+        if (!light.isOn) continue;
+        lighting += phong(light.ambient * ambient_weight, light.diffuse, light.specular,
+                          normalize(-light.direction), norm, view_dirction);
+    }
+    for (int i = 0; i < pointLightCount; i++) {
+        PointLight light = pointLights[i];
+        if (!light.isOn) continue;
+        vec3 light_direction = normalize(light.position - world_coord);
+        lighting += phong(light.ambient * ambient_weight, light.diffuse, light.specular,
+                          light_direction, norm, view_dirction)
+                    * attenuation(light.position, world_coord, light.constant, light.linear, light.quadratic);
+    }
+    for (int i = 0; i < spotLightCount; i++) {
+        SpotLight light = spotLights[i];
+        if (!light.isOn) continue;
+        vec3 light_direction = normalize(light.position - world_coord);
+        // 内外切光角之间平滑过渡
+        float theta = dot(light_direction, normalize(-light.direction));
+        // 内外切光角相等时避免除以零
+        float epsilon = max(light.cutOff - light.outerCutOff, 1e-4);
+        float intensity = clamp((theta - light.outerCutOff) / epsilon, 0.0, 1.0);
+        lighting += (light.ambient * ambient_weight
+                     + phong(vec3(0.0), light.diffuse, light.specular, light_direction, norm, view_dirction)
+                       * intensity)
+                    * attenuation(light.position, world_coord, light.constant, light.linear, light.quadratic);
+    }
+
+    return lighting;
+}
@@ -3,13 +3,13 @@ out vec4 fragColor;
 in VS_OUTPUT {
     vec2 TexCoord;
 } IN;
-uniform sampler2D texture0;
-uniform sampler2D texture1;
+// 第0层为木箱，第1层为笑脸
+uniform sampler2DArray textures;
 void main()
 {
     // fragColor = IN.Color;
-    // fragColor = texture(texture0, IN.TexCoord);
-    // fragColor = texture(texture0, IN.TexCoord) * IN.Color;
-    // fragColor =  texture(texture1, IN.TexCoord) * texture(texture0, IN.TexCoord);
-    fragColor = mix(texture(texture0, IN.TexCoord), texture(texture1, IN.TexCoord), 0.2);
+    // fragColor = texture(textures, vec3(IN.TexCoord, 0));
+    // fragColor = texture(textures, vec3(IN.TexCoord, 0)) * IN.Color;
+    // fragColor =  texture(textures, vec3(IN.TexCoord, 1)) * texture(textures, vec3(IN.TexCoord, 0));
+    fragColor = mix(texture(textures, vec3(IN.TexCoord, 0)), texture(textures, vec3(IN.TexCoord, 1)), 0.2);
 }
@@ -0,0 +1,46 @@
+#version 450 core
+// 镜面反射BRDF的积分查找表，x为NdotV，y为粗糙度，结果为F0的缩放与偏移
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 FragColor;
+
+#import "common.glsl"
+
+const uint SAMPLE_COUNT = 1024u;
+
+float geometrySchlickGGX(float NdotV, float roughness)
+{
+    // IBL使用的k与直接光照不同
+    float k = (roughness * roughness) / 2.0;
+    return NdotV / (NdotV * (1.0 - k) + k);
+}
+
+vec2 integrateBRDF(float NdotV, float roughness)
+{
+    vec3 V = vec3(sqrt(1.0 - NdotV * NdotV), 0.0, NdotV);
+    vec3 N = vec3(0.0, 0.0, 1.0);
+    float A = 0.0;
+    float B = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec2 xi = hammersley(i, SAMPLE_COUNT);
+        vec3 H = importanceSampleGGX(xi, N, roughness);
+        vec3 L = normalize(2.0 * dot(V, H) * H - V);
+        float NdotL = max(L.z, 0.0);
+        float NdotH = max(H.z, 0.0);
+        float VdotH = max(dot(V, H), 0.0);
+        if (NdotL > 0.0) {
+            float G = geometrySchlickGGX(NdotV, roughness) * geometrySchlickGGX(NdotL, roughness);
+            float G_Vis = (G * VdotH) / (NdotH * NdotV);
+            float Fc = pow(1.0 - VdotH, 5.0);
+            A += (1.0 - Fc) * G_Vis;
+            B += Fc * G_Vis;
+        }
+    }
+    return vec2(A, B) / float(SAMPLE_COUNT);
+}
+
+void main()
+{
+    FragColor = vec4(integrateBRDF(max(IN.TexCoord.x, 0.001), IN.TexCoord.y), 0.0, 1.0);
+}
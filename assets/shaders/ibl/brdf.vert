@@ -0,0 +1,11 @@
+#version 450 core
+// 不需要顶点数据，由gl_VertexID生成覆盖全屏的三角形
+out VS_OUTPUT {
+    vec2 TexCoord;
+} OUT;
+void main()
+{
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    OUT.TexCoord = pos;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
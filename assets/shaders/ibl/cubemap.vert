@@ -0,0 +1,13 @@
+#version 450 core
+layout (location = 0) in vec3 Position;
+
+// 朝向立方体贴图某一面的 proj * view
+uniform mat4 view_proj;
+out VS_OUTPUT {
+    vec3 LocalPos;
+} OUT;
+void main()
+{
+    OUT.LocalPos = Position;
+    gl_Position = view_proj * vec4(Position, 1.0);
+}
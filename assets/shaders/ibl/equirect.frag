@@ -0,0 +1,18 @@
+#version 450 core
+// 将等距柱状投影(equirectangular)的全景图转换到立方体贴图的一面
+in VS_OUTPUT {
+    vec3 LocalPos;
+} IN;
+out vec4 FragColor;
+
+uniform sampler2D equirectMap;
+
+const vec2 invAtan = vec2(0.1591, 0.3183);
+void main()
+{
+    vec3 dir = normalize(IN.LocalPos);
+    vec2 uv = vec2(atan(dir.z, dir.x), asin(dir.y)) * invAtan + 0.5;
+    // 纹理的第一行为全景图的顶部
+    uv.y = 1.0 - uv.y;
+    FragColor = vec4(texture(equirectMap, uv).rgb, 1.0);
+}
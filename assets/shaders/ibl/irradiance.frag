@@ -0,0 +1,32 @@
+#version 450 core
+// 对半球内的入射光做余弦加权的卷积，得到漫反射辐照度
+in VS_OUTPUT {
+    vec3 LocalPos;
+} IN;
+out vec4 FragColor;
+
+uniform samplerCube environmentMap;
+
+#import "common.glsl"
+
+const float sampleDelta = 0.025;
+void main()
+{
+    vec3 normal = normalize(IN.LocalPos);
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    vec3 irradiance = vec3(0.0);
+    float count = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += sampleDelta) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += sampleDelta) {
+            vec3 tangentSample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 sampleVec = tangentSample.x * right + tangentSample.y * up + tangentSample.z * normal;
+            // 环境贴图带有mipmap，采样较低的层级以减少噪点
+            irradiance += textureLod(environmentMap, sampleVec, 2.0).rgb * cos(theta) * sin(theta);
+            count++;
+        }
+    }
+    FragColor = vec4(PI * irradiance / count, 1.0);
+}
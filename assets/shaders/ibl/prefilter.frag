@@ -0,0 +1,41 @@
+#version 450 core
+// 按粗糙度对环境贴图做GGX预过滤，每层mip对应一个粗糙度
+in VS_OUTPUT {
+    vec3 LocalPos;
+} IN;
+out vec4 FragColor;
+
+uniform samplerCube environmentMap;
+uniform float roughness;
+// 环境贴图第0层的边长
+uniform float resolution;
+
+#import "common.glsl"
+
+const uint SAMPLE_COUNT = 512u;
+void main()
+{
+    // 假设视线方向与反射方向均等于法线
+    vec3 N = normalize(IN.LocalPos);
+    vec3 V = N;
+
+    vec3 color = vec3(0.0);
+    float totalWeight = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec2 xi = hammersley(i, SAMPLE_COUNT);
+        vec3 H = importanceSampleGGX(xi, N, roughness);
+        vec3 L = normalize(2.0 * dot(V, H) * H - V);
+        float NdotL = dot(N, L);
+        if (NdotL > 0.0) {
+            // 按采样的概率密度选择环境贴图的mip层级，避免亮点
+            float NdotH = max(dot(N, H), 0.0);
+            float pdf = distributionGGX(NdotH, roughness) / 4.0 + 0.0001;
+            float saTexel = 4.0 * PI / (6.0 * resolution * resolution);
+            float saSample = 1.0 / (float(SAMPLE_COUNT) * pdf + 0.0001);
+            float mip = roughness == 0.0 ? 0.0 : 0.5 * log2(saSample / saTexel);
+            color += textureLod(environmentMap, L, mip).rgb * NdotL;
+            totalWeight += NdotL;
+        }
+    }
+    FragColor = vec4(color / totalWeight, 1.0);
+}
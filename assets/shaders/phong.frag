@@ -9,30 +9,24 @@ in VS_OUTPUT {
 out vec4 FragColor;
 
 uniform sampler2D texture0;
+uniform vec4 baseColor;
 
-uniform vec3 lightPos;
-uniform vec3 lightColor;
-uniform vec3 viewPos;
+#import "common/camera.glsl"
+#import "common/lighting.glsl"
+#import "common/ibl.glsl"
 
 void main()
 {
-
-    float ambientStrength = 0.1;
-    vec3 ambient = lightColor * ambientStrength;
-
     vec3 norm = normalize(IN.Normal);
-    vec3 light_direction = normalize(lightPos - IN.WorldCoord);
-    float diff = max(dot(norm,light_direction),0.0);
-    vec3 diffuse = diff * lightColor;
-
-    float specularStrength = 0.5;
-    vec3 view_dirction = normalize(viewPos - IN.WorldCoord);
-    vec3 reflect_direction = reflect(-light_direction, norm);
-    float spec = pow(max(dot(view_dirction,reflect_direction),0.0),256);
-    vec3 specular = specularStrength * spec * lightColor;
+    vec3 view_dirction = normalize(viewPos.xyz - IN.WorldCoord);
+    vec3 lighting = calcLighting(norm, view_dirction, IN.WorldCoord, lightAmbientWeight());
 
-    vec3 objectColor = vec3(texture(texture0, IN.TexCoord));
+    vec3 objectColor = vec3(texture(texture0, IN.TexCoord) * baseColor);
 
-    vec3 result = objectColor * (ambient + diffuse + specular);
-    FragColor = min(vec4(result,1.0), vec4(1.0));
+    // 开启IBL时环境光由IBL提供，否则使用光源的环境光分量，粗糙度由高光指数换算
+    float roughness = sqrt(2.0 / (shininess + 2.0));
+    vec3 result = objectColor * lighting
+                  + ambientLighting(norm, view_dirction, objectColor, roughness);
+    // 不截断，超出1的部分由色调映射处理
+    FragColor = vec4(result, 1.0);
 }
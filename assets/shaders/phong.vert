@@ -4,7 +4,7 @@ layout (location = 0) in vec3 Position;
 layout (location = 1) in vec2 TexCoord;
 layout (location = 2) in vec3 Normal;
 
-uniform mat4 vp_proj;
+#import "common/camera.glsl"
 uniform mat4 m_proj;
 uniform mat3 NormalMat;
 
@@ -17,7 +17,7 @@ out VS_OUTPUT {
 void main()
 {
     // 补充的w分量用于透视除法(Perspective Division)
-    gl_Position = m_proj * vp_proj * vec4(Position, 1.0);
+    gl_Position = vp_proj * m_proj * vec4(Position, 1.0);
     OUT.TexCoord = TexCoord;
     OUT.WorldCoord = vec3( m_proj * vec4(Position, 1.0));
     OUT.Normal = normalize(NormalMat * Normal);
@@ -0,0 +1,17 @@
+#version 450 core
+// 将模糊后的高亮部分叠加回原画面
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main()
+{
+    vec3 color = texture(frame, IN.TexCoord).rgb;
+    color += texture(bloom, IN.TexCoord).rgb * intensity;
+    fragColor = vec4(color, 1.0);
+}
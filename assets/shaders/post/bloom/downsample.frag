@@ -0,0 +1,33 @@
+#version 450 core
+// 13次采样的降采样滤波(Jimenez 2014)，减少闪烁
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+
+void main()
+{
+    vec2 texel = 1.0 / vec2(textureSize(frame, 0));
+    vec2 uv = IN.TexCoord;
+    vec3 a = texture(frame, uv + texel * vec2(-2.0, 2.0)).rgb;
+    vec3 b = texture(frame, uv + texel * vec2(0.0, 2.0)).rgb;
+    vec3 c = texture(frame, uv + texel * vec2(2.0, 2.0)).rgb;
+    vec3 d = texture(frame, uv + texel * vec2(-2.0, 0.0)).rgb;
+    vec3 e = texture(frame, uv).rgb;
+    vec3 f = texture(frame, uv + texel * vec2(2.0, 0.0)).rgb;
+    vec3 g = texture(frame, uv + texel * vec2(-2.0, -2.0)).rgb;
+    vec3 h = texture(frame, uv + texel * vec2(0.0, -2.0)).rgb;
+    vec3 i = texture(frame, uv + texel * vec2(2.0, -2.0)).rgb;
+    vec3 j = texture(frame, uv + texel * vec2(-1.0, 1.0)).rgb;
+    vec3 k = texture(frame, uv + texel * vec2(1.0, 1.0)).rgb;
+    vec3 l = texture(frame, uv + texel * vec2(-1.0, -1.0)).rgb;
+    vec3 m = texture(frame, uv + texel * vec2(1.0, -1.0)).rgb;
+
+    vec3 color = e * 0.125;
+    color += (a + c + g + i) * 0.03125;
+    color += (b + d + f + h) * 0.0625;
+    color += (j + k + l + m) * 0.125;
+    fragColor = vec4(color, 1.0);
+}
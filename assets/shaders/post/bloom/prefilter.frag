@@ -0,0 +1,21 @@
+#version 450 core
+// 提取亮度超过阈值的部分，并降采样到一半分辨率
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+uniform float threshold;
+// 阈值附近的平滑过渡宽度
+uniform float knee;
+
+void main()
+{
+    vec3 color = texture(frame, IN.TexCoord).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.0001);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 0.0001);
+    fragColor = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,26 @@
+#version 450 core
+// 3x3帐篷滤波的升采样，结果以加法混合叠加到上一级
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+// 采样间隔，以较低一级的像素为单位
+uniform float radius;
+
+void main()
+{
+    vec2 texel = radius / vec2(textureSize(frame, 0));
+    vec2 uv = IN.TexCoord;
+    vec3 color = texture(frame, uv).rgb * 4.0;
+    color += (texture(frame, uv + vec2(0.0, texel.y)).rgb
+              + texture(frame, uv - vec2(0.0, texel.y)).rgb
+              + texture(frame, uv + vec2(texel.x, 0.0)).rgb
+              + texture(frame, uv - vec2(texel.x, 0.0)).rgb) * 2.0;
+    color += texture(frame, uv + texel).rgb
+             + texture(frame, uv - texel).rgb
+             + texture(frame, uv + vec2(texel.x, -texel.y)).rgb
+             + texture(frame, uv + vec2(-texel.x, texel.y)).rgb;
+    fragColor = vec4(color / 16.0, 1.0);
+}
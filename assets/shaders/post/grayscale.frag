@@ -0,0 +1,16 @@
+#version 450 core
+// 灰度，按人眼对各通道的敏感度加权
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+uniform float strength;
+
+void main()
+{
+    vec3 color = texture(frame, IN.TexCoord).rgb;
+    float gray = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    fragColor = vec4(mix(color, vec3(gray), strength), 1.0);
+}
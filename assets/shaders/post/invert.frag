@@ -0,0 +1,16 @@
+#version 450 core
+// 反相
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+// 与原图混合的比例
+uniform float strength;
+
+void main()
+{
+    vec3 color = texture(frame, IN.TexCoord).rgb;
+    fragColor = vec4(mix(color, 1.0 - color, strength), 1.0);
+}
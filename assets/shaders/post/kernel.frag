@@ -0,0 +1,28 @@
+#version 450 core
+// 3x3卷积核，模糊、锐化与边缘检测共用
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+// 按行排列，由 render_gl/post.rs 上传
+uniform float kernel[9];
+// 采样间隔，以像素为单位
+uniform float offset;
+uniform float strength;
+
+void main()
+{
+    vec2 texel = offset / vec2(textureSize(frame, 0));
+    vec3 color = texture(frame, IN.TexCoord).rgb;
+    vec3 result = vec3(0.0);
+    for (int y = 0; y < 3; y++) {
+        for (int x = 0; x < 3; x++) {
+            // 第一行为上方的像素
+            vec2 uv = IN.TexCoord + vec2(x - 1, 1 - y) * texel;
+            result += texture(frame, uv).rgb * kernel[y * 3 + x];
+        }
+    }
+    fragColor = vec4(mix(color, result, strength), 1.0);
+}
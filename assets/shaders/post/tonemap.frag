@@ -0,0 +1,52 @@
+#version 450 core
+// 将HDR画面映射到[0, 1]，再编码为sRGB
+in VS_OUTPUT {
+    vec2 TexCoord;
+} IN;
+out vec4 fragColor;
+
+uniform sampler2D frame;
+uniform float exposure;
+// 与 render_gl/tonemap.rs 中 ToneMapping 的顺序一致
+uniform int toneMapping;
+
+vec3 reinhard(vec3 color)
+{
+    return color / (color + 1.0);
+}
+
+// Krzysztof Narkowicz 对ACES曲线的拟合
+vec3 aces(vec3 color)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+vec3 exponential(vec3 color)
+{
+    return 1.0 - exp(-color);
+}
+
+vec3 linearToSrgb(vec3 color)
+{
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+void main()
+{
+    vec3 color = texture(frame, IN.TexCoord).rgb * exposure;
+    if (toneMapping == 0) {
+        color = reinhard(color);
+    } else if (toneMapping == 1) {
+        color = aces(color);
+    } else {
+        color = exponential(color);
+    }
+    fragColor = vec4(linearToSrgb(clamp(color, 0.0, 1.0)), 1.0);
+}
@@ -0,0 +1,10 @@
+#version 450 core
+out vec4 fragColor;
+in VS_OUTPUT {
+    vec3 TexCoord;
+} IN;
+uniform samplerCube skybox;
+void main()
+{
+    fragColor = vec4(texture(skybox, IN.TexCoord).rgb, 1.0);
+}
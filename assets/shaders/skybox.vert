@@ -0,0 +1,15 @@
+#version 450 core
+layout (location = 0) in vec3 Position;
+
+// 已去除平移的 proj * view
+uniform mat4 view_proj;
+out VS_OUTPUT {
+    vec3 TexCoord;
+} OUT;
+void main()
+{
+    OUT.TexCoord = Position;
+    vec4 pos = view_proj * vec4(Position, 1.0);
+    // z取w，透视除法后深度恒为1.0，即位于远平面上
+    gl_Position = pos.xyww;
+}
@@ -2,7 +2,7 @@
 layout (location = 0) in vec3 Position;
 layout (location = 1) in vec2 TexCoord;
 
-uniform mat4 vp_proj;
+#import "common/camera.glsl"
 uniform mat4 m_proj;
 
 out VS_OUTPUT {
@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Context};
+
+use crate::recorder::Format;
+
+/// 命令行参数
+/// --headless            不创建可见窗口，也不处理输入与egui
+/// --frames <N>          无窗口模式下渲染的帧数，默认为1
+/// --scene <名称|索引>   初始场景
+/// --size <宽>x<高>      窗口(或离屏帧缓冲)的大小
+/// --time <秒>           固定场景时间，使无窗口模式的渲染结果可复现
+/// --record <png|y4m>    无窗口模式下录制每一帧
+/// --fps <N>             录制的帧率(模拟时间步长)，默认为60
+#[derive(Debug)]
+pub struct Options {
+  pub headless: bool,
+  pub frames: u32,
+  pub scene: Option<String>,
+  pub width: u32,
+  pub height: u32,
+  pub time: Option<f32>,
+  pub record: Option<Format>,
+  pub fps: u32,
+}
+impl Default for Options {
+  fn default() -> Self {
+    Options {
+      headless: false,
+      frames: 1,
+      scene: None,
+      width: 1920,
+      height: 1200,
+      time: None,
+      record: None,
+      fps: 60,
+    }
+  }
+}
+
+impl Options {
+  pub fn from_args() -> Result<Options, anyhow::Error> {
+    Self::parse(std::env::args().skip(1))
+  }
+
+  pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, anyhow::Error> {
+    let mut options = Options::default();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+      let mut value = || args.next().ok_or_else(|| anyhow!("参数 {} 缺少取值", arg));
+      match arg.as_str() {
+        "--headless" => options.headless = true,
+        "--frames" => {
+          options.frames = value()?.parse().context("--frames 需要一个整数")?;
+        }
+        "--scene" => options.scene = Some(value()?),
+        "--size" => {
+          let size = value()?;
+          let (w, h) = size
+            .split_once('x')
+            .ok_or_else(|| anyhow!("--size 的格式应为 <宽>x<高>: {}", size))?;
+          options.width = w.parse().context("无效的宽度")?;
+          options.height = h.parse().context("无效的高度")?;
+        }
+        "--time" => {
+          options.time = Some(value()?.parse().context("--time 需要一个数值")?);
+        }
+        "--record" => {
+          let format = value()?;
+          options.record =
+            Some(Format::parse(&format).ok_or_else(|| anyhow!("未知的录制格式 {}", format))?);
+        }
+        "--fps" => {
+          options.fps = value()?.parse().context("--fps 需要一个整数")?;
+        }
+        _ => return Err(anyhow!("未知参数 {}", arg)),
+      }
+    }
+    Ok(options)
+  }
+}
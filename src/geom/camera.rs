@@ -1,9 +1,13 @@
+use crate::render_gl::buffer::{self, UniformBuffer};
 use crate::time;
-use na::{Matrix4, Point3, Vector3};
+use na::{Matrix4, Point3, Vector3, Vector4};
 use sdl2::keyboard::Keycode;
 
 use super::input;
 
+/// 着色器中摄像机uniform块的名称
+pub const CAMERA_BLOCK: &str = "Camera";
+
 pub struct Camera {
   // 摄像机的位置
   pub eye: Point3<f32>,
@@ -108,6 +112,14 @@ impl Camera {
   pub fn get_view_mat(&self) -> Matrix4<f32> {
     Matrix4::look_at_rh(&self.eye, &(&self.eye + &self.toward), &self.up)
   }
+  // 去除平移的视图矩阵，用于天空盒等位于无限远处的物体
+  pub fn get_rotation_view_mat(&self) -> Matrix4<f32> {
+    let mut view = self.get_view_mat();
+    for row in 0..3 {
+      view[(row, 3)] = 0.0;
+    }
+    view
+  }
   // 获得透视投影矩阵
   // aspect: 宽高比
   pub fn get_proj_mat(&self, aspect: f32) -> Matrix4<f32> {
@@ -117,3 +129,42 @@ impl Camera {
     self.get_proj_mat(aspect) * self.get_view_mat()
   }
 }
+
+/// 与着色器中 std140 布局的Camera块一一对应
+/// layout (std140) uniform Camera { mat4 proj; mat4 view; mat4 vp_proj; vec4 viewPos; };
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct CameraBlock {
+  pub proj: Matrix4<f32>,
+  pub view: Matrix4<f32>,
+  pub vp: Matrix4<f32>,
+  pub eye: Vector4<f32>,
+}
+
+/// 所有着色程序共享的摄像机UBO，每帧只需上传一次
+pub struct CameraUniform {
+  buffer: UniformBuffer,
+}
+impl CameraUniform {
+  pub fn new() -> Result<Self, buffer::Error> {
+    let buffer = UniformBuffer::new();
+    buffer.bind();
+    buffer.allocate(std::mem::size_of::<CameraBlock>(), glow::DYNAMIC_DRAW);
+    buffer.unbind();
+    buffer.bind_base(buffer::uniform_binding(CAMERA_BLOCK)?);
+    Ok(CameraUniform { buffer })
+  }
+  pub fn update(&self, camera: &Camera, aspect: f32) {
+    let proj = camera.get_proj_mat(aspect);
+    let view = camera.get_view_mat();
+    let block = CameraBlock {
+      proj,
+      view,
+      vp: proj * view,
+      eye: camera.eye.to_homogeneous(),
+    };
+    self.buffer.bind();
+    self.buffer.sub_data(0, &[block]);
+    self.buffer.unbind();
+  }
+}
@@ -1,23 +1,78 @@
-use na::Vector3;
+use another::ui;
+use na::{Matrix4, Vector3};
+
+use crate::render_gl::buffer::{self, UniformBuffer};
+
+// 与shaders/common/lighting.glsl中的数组长度保持一致
+pub const MAX_DIRECT_LIGHTS: usize = 4;
+pub const MAX_POINT_LIGHTS: usize = 8;
+pub const MAX_SPOT_LIGHTS: usize = 4;
+
+/// 着色器中光源uniform块的名称
+pub const LIGHTS_BLOCK: &str = "Lights";
 
 #[derive(Copy, Clone, Debug)]
-#[repr(C, packed)]
 pub struct Light {
   pub is_on: bool,
   pub ambient: Vector3<f32>,
   pub diffuse: Vector3<f32>,
   pub specular: Vector3<f32>,
 }
+impl Light {
+  /// 由单一颜色生成光源，环境光分量取其十分之一，开启IBL时不生效
+  pub fn from_color(color: Vector3<f32>) -> Light {
+    Light {
+      is_on: true,
+      ambient: color * 0.1,
+      diffuse: color,
+      specular: color,
+    }
+  }
+  fn std140(&self) -> LightStd140 {
+    LightStd140 {
+      ambient: self.ambient.into(),
+      is_on: self.is_on as u32,
+      diffuse: self.diffuse.into(),
+      specular: self.specular.into(),
+      ..Default::default()
+    }
+  }
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    ui.checkbox(&mut self.is_on, "开启");
+    edit_color(ui, "环境光", &mut self.ambient);
+    edit_color(ui, "漫反射", &mut self.diffuse);
+    edit_color(ui, "镜面反射", &mut self.specular);
+  }
+}
+
 #[derive(Copy, Clone, Debug)]
-#[repr(C, packed)]
 pub struct DirectLight {
   pub light: Light,
 
   pub direction: Vector3<f32>,
 }
+impl DirectLight {
+  pub fn new(color: Vector3<f32>, direction: Vector3<f32>) -> DirectLight {
+    DirectLight {
+      light: Light::from_color(color),
+      direction,
+    }
+  }
+  fn std140(&self) -> DirectLightStd140 {
+    DirectLightStd140 {
+      light: self.light.std140(),
+      direction: self.direction.into(),
+      ..Default::default()
+    }
+  }
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    self.light.edit(ui);
+    ui.label("方向");
+    ui::edit_vec3(ui, &mut self.direction, -1.0..=1.0);
+  }
+}
 
 #[derive(Copy, Clone, Debug)]
-#[repr(C, packed)]
 pub struct PointLight {
   pub light: Light,
 
@@ -26,8 +81,39 @@ pub struct PointLight {
   pub linear: f32,
   pub quadratic: f32,
 }
+impl PointLight {
+  /// 不衰减的点光源，位置由场景图节点决定
+  pub fn new(color: Vector3<f32>) -> PointLight {
+    PointLight {
+      light: Light::from_color(color),
+      position: Vector3::zeros(),
+      constant: 1.0,
+      linear: 0.0,
+      quadratic: 0.0,
+    }
+  }
+  fn std140(&self) -> PointLightStd140 {
+    PointLightStd140 {
+      light: self.light.std140(),
+      position: self.position.into(),
+      constant: self.constant,
+      linear: self.linear,
+      quadratic: self.quadratic,
+      ..Default::default()
+    }
+  }
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    self.light.edit(ui);
+    edit_attenuation(
+      ui,
+      &mut self.constant,
+      &mut self.linear,
+      &mut self.quadratic,
+    );
+  }
+}
+
 #[derive(Copy, Clone, Debug)]
-#[repr(C, packed)]
 pub struct SpotLight {
   pub light: Light,
 
@@ -37,6 +123,241 @@ pub struct SpotLight {
   pub constant: f32,
   pub linear: f32,
   pub quadratic: f32,
+  // 内外切光角(角度制)，上传时转换为余弦值
   pub cut_off: f32,
   pub outer_cut_off: f32,
 }
+impl SpotLight {
+  pub fn new(color: Vector3<f32>, direction: Vector3<f32>) -> SpotLight {
+    SpotLight {
+      light: Light::from_color(color),
+      position: Vector3::zeros(),
+      direction,
+      constant: 1.0,
+      linear: 0.0,
+      quadratic: 0.0,
+      cut_off: 12.5,
+      outer_cut_off: 17.5,
+    }
+  }
+  /// 切光角以余弦值上传
+  fn std140(&self) -> SpotLightStd140 {
+    SpotLightStd140 {
+      light: self.light.std140(),
+      position: self.position.into(),
+      constant: self.constant,
+      direction: self.direction.into(),
+      linear: self.linear,
+      quadratic: self.quadratic,
+      cut_off: self.cut_off.to_radians().cos(),
+      outer_cut_off: self.outer_cut_off.to_radians().cos(),
+      ..Default::default()
+    }
+  }
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    self.light.edit(ui);
+    ui.label("方向");
+    ui::edit_vec3(ui, &mut self.direction, -1.0..=1.0);
+    edit_attenuation(
+      ui,
+      &mut self.constant,
+      &mut self.linear,
+      &mut self.quadratic,
+    );
+    ui.add(egui::Slider::new(&mut self.cut_off, 0.0..=90.0).text("内切光角"));
+    ui.add(egui::Slider::new(&mut self.outer_cut_off, 0.0..=90.0).text("外切光角"));
+    if self.outer_cut_off < self.cut_off {
+      self.outer_cut_off = self.cut_off;
+    }
+  }
+}
+
+/// 可挂载在场景图节点上的光源
+#[derive(Copy, Clone, Debug)]
+pub enum LightSource {
+  Direct(DirectLight),
+  Point(PointLight),
+  Spot(SpotLight),
+}
+impl LightSource {
+  pub fn light(&self) -> &Light {
+    match self {
+      LightSource::Direct(l) => &l.light,
+      LightSource::Point(l) => &l.light,
+      LightSource::Spot(l) => &l.light,
+    }
+  }
+  pub fn kind_name(&self) -> &'static str {
+    match self {
+      LightSource::Direct(_) => "平行光",
+      LightSource::Point(_) => "点光源",
+      LightSource::Spot(_) => "聚光灯",
+    }
+  }
+  /// 以节点的世界矩阵变换光源的位置与方向
+  pub fn to_world(&self, world: &Matrix4<f32>) -> LightSource {
+    let position = world.fixed_slice::<3, 1>(0, 3).into_owned();
+    let rotate = |dir: &Vector3<f32>| world.transform_vector(dir);
+    match *self {
+      LightSource::Direct(l) => LightSource::Direct(DirectLight {
+        direction: rotate(&l.direction),
+        ..l
+      }),
+      LightSource::Point(l) => LightSource::Point(PointLight { position, ..l }),
+      LightSource::Spot(l) => LightSource::Spot(SpotLight {
+        position,
+        direction: rotate(&l.direction),
+        ..l
+      }),
+    }
+  }
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    match self {
+      LightSource::Direct(l) => l.edit(ui),
+      LightSource::Point(l) => l.edit(ui),
+      LightSource::Spot(l) => l.edit(ui),
+    }
+  }
+}
+
+// 以下结构体与 shaders/common/lighting.glsl 中 std140 布局的结构体一一对应
+// vec3 按16字节对齐，其后的标量可占用剩余的4字节，结构体大小为16的倍数
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+struct LightStd140 {
+  ambient: [f32; 3],
+  is_on: u32,
+  diffuse: [f32; 3],
+  _pad0: f32,
+  specular: [f32; 3],
+  _pad1: f32,
+}
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+struct DirectLightStd140 {
+  light: LightStd140,
+  direction: [f32; 3],
+  _pad: f32,
+}
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+struct PointLightStd140 {
+  light: LightStd140,
+  position: [f32; 3],
+  constant: f32,
+  linear: f32,
+  quadratic: f32,
+  _pad: [f32; 2],
+}
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+struct SpotLightStd140 {
+  light: LightStd140,
+  position: [f32; 3],
+  constant: f32,
+  direction: [f32; 3],
+  linear: f32,
+  quadratic: f32,
+  cut_off: f32,
+  outer_cut_off: f32,
+  _pad: f32,
+}
+
+/// 与着色器中 std140 布局的Lights块一一对应
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct LightsBlock {
+  dir_lights: [DirectLightStd140; MAX_DIRECT_LIGHTS],
+  point_lights: [PointLightStd140; MAX_POINT_LIGHTS],
+  spot_lights: [SpotLightStd140; MAX_SPOT_LIGHTS],
+  // 各类光源的数量
+  counts: [i32; 3],
+  _pad: i32,
+}
+impl LightsBlock {
+  /// 将光源按类型放入各数组，超出数组长度的光源将被忽略
+  pub fn new(lights: impl Iterator<Item = LightSource>) -> LightsBlock {
+    let mut block = LightsBlock::default();
+    let (mut direct, mut point, mut spot) = (0, 0, 0);
+    for light in lights {
+      match light {
+        LightSource::Direct(l) if direct < MAX_DIRECT_LIGHTS => {
+          block.dir_lights[direct] = l.std140();
+          direct += 1;
+        }
+        LightSource::Point(l) if point < MAX_POINT_LIGHTS => {
+          block.point_lights[point] = l.std140();
+          point += 1;
+        }
+        LightSource::Spot(l) if spot < MAX_SPOT_LIGHTS => {
+          block.spot_lights[spot] = l.std140();
+          spot += 1;
+        }
+        _ => {}
+      }
+    }
+    block.counts = [direct as i32, point as i32, spot as i32];
+    block
+  }
+}
+
+/// 光源UBO，着色程序需绑定 LIGHTS_BLOCK 块
+pub struct LightsUniform {
+  buffer: UniformBuffer,
+  binding: u32,
+}
+impl LightsUniform {
+  pub fn new() -> Result<Self, buffer::Error> {
+    let buffer = UniformBuffer::new();
+    buffer.bind();
+    buffer.allocate(std::mem::size_of::<LightsBlock>(), glow::DYNAMIC_DRAW);
+    buffer.unbind();
+    Ok(LightsUniform {
+      buffer,
+      binding: buffer::uniform_binding(LIGHTS_BLOCK)?,
+    })
+  }
+  /// 上传光源并绑定到 LIGHTS_BLOCK 的binding点，各场景的光源UBO共用该binding点
+  pub fn update(&self, lights: impl Iterator<Item = LightSource>) {
+    let block = LightsBlock::new(lights);
+    self.buffer.bind();
+    self.buffer.sub_data(0, &[block]);
+    self.buffer.unbind();
+    self.buffer.bind_base(self.binding);
+  }
+}
+
+/// 颜色与强度分开编辑，强度为最亮通道的值，大于1时为HDR颜色
+fn edit_color(ui: &mut egui::Ui, label: &str, color: &mut Vector3<f32>) {
+  ui.horizontal(|ui| {
+    ui.label(label);
+    let scale = color.max().max(1.0);
+    let mut rgb = *color / scale;
+    if ui
+      .color_edit_button_rgb(rgb.as_mut_slice().try_into().unwrap())
+      .changed()
+    {
+      *color = rgb * scale;
+    }
+    let mut intensity = scale;
+    if ui
+      .add(
+        egui::DragValue::new(&mut intensity)
+          .speed(0.05)
+          .clamp_range(1.0..=20.0)
+          .prefix("强度 "),
+      )
+      .changed()
+    {
+      let max = rgb.max();
+      let base = if max > 0.0 { rgb / max } else { rgb };
+      *color = base * intensity;
+    }
+  });
+}
+
+fn edit_attenuation(ui: &mut egui::Ui, constant: &mut f32, linear: &mut f32, quadratic: &mut f32) {
+  ui.add(egui::Slider::new(constant, 0.0..=2.0).text("常数项"));
+  ui.add(egui::Slider::new(linear, 0.0..=1.0).text("一次项"));
+  ui.add(egui::Slider::new(quadratic, 0.0..=0.1).text("二次项"));
+}
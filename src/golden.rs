@@ -0,0 +1,168 @@
+// 场景的参考图像(golden image)回归测试
+//
+// 以固定的时间与各场景的初始摄像机渲染所有场景，与 tests/golden/<场景名>.png 逐像素比较。
+// 参考图像缺失时测试失败，设置 GOLDEN_UPDATE=1 可生成或覆盖参考图像。
+// 比较失败时将实际结果与差异图写入 target/golden。
+// 以无窗口模式创建GL上下文，无法创建时打印原因并跳过；设置 GOLDEN_REQUIRED=1 时改为失败(CI中使用)。
+// 参考图像以 LIBGL_ALWAYS_SOFTWARE=1 (Mesa llvmpipe) 生成，运行时应设置相同的环境变量。
+
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+use crate::cli::Options;
+use crate::geom::camera::CameraUniform;
+use crate::render_gl::ibl::Environment;
+use crate::render_gl::offscreen::OffScreen;
+use crate::render_gl::skybox::Skybox;
+use crate::render_gl::Viewport;
+use crate::resources::Resources;
+use crate::{scene, time, ENVIRONMENT, SKYBOX};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 200;
+// 渲染时固定的时间(秒)
+const FIXED_TIME: f32 = 1.0;
+// 单个通道允许的最大差值
+const TOLERANCE: u8 = 8;
+// 允许超出容差的像素比例
+const MAX_MISMATCH_RATIO: f64 = 0.001;
+
+fn manifest_dir() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// 场景名转换为文件名
+fn file_stem(name: &str) -> String {
+  name
+    .chars()
+    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+    .collect()
+}
+
+/// 返回超出容差的像素数，以及标出这些像素的差异图
+fn compare(actual: &RgbaImage, expected: &RgbaImage) -> (usize, RgbaImage) {
+  let mut mismatch = 0;
+  let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+    let a = actual.get_pixel(x, y);
+    let e = expected.get_pixel(x, y);
+    let exceeded = a
+      .0
+      .iter()
+      .zip(e.0.iter())
+      .any(|(a, e)| a.abs_diff(*e) > TOLERANCE);
+    if exceeded {
+      mismatch += 1;
+      Rgba([255, 0, 0, 255])
+    } else {
+      // 未超出容差处以暗淡的灰度显示
+      let luma = ((a[0] as u32 + a[1] as u32 + a[2] as u32) / 12) as u8;
+      Rgba([luma, luma, luma, 255])
+    }
+  });
+  (mismatch, diff)
+}
+
+fn check(
+  name: &str,
+  actual: &RgbaImage,
+  golden_dir: &Path,
+  output_dir: &Path,
+) -> Result<(), String> {
+  let stem = file_stem(name);
+  let reference = golden_dir.join(format!("{}.png", stem));
+  if std::env::var_os("GOLDEN_UPDATE").is_some() {
+    actual.save(&reference).map_err(|e| e.to_string())?;
+    println!("已写入参考图像 {:?}", reference);
+    return Ok(());
+  }
+  if !reference.exists() {
+    return Err(format!(
+      "{}: 缺少参考图像 {:?}，设置 GOLDEN_UPDATE=1 生成",
+      name, reference
+    ));
+  }
+  let expected = image::open(&reference)
+    .map_err(|e| format!("{}: 参考图像读取失败 {}", name, e))?
+    .to_rgba8();
+  if expected.dimensions() != actual.dimensions() {
+    return Err(format!(
+      "{}: 尺寸不一致 {:?} != {:?}",
+      name,
+      actual.dimensions(),
+      expected.dimensions()
+    ));
+  }
+  let (mismatch, diff) = compare(actual, &expected);
+  let ratio = mismatch as f64 / (WIDTH * HEIGHT) as f64;
+  if ratio <= MAX_MISMATCH_RATIO {
+    return Ok(());
+  }
+  std::fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+  let actual_path = output_dir.join(format!("{}.actual.png", stem));
+  let diff_path = output_dir.join(format!("{}.diff.png", stem));
+  actual.save(&actual_path).map_err(|e| e.to_string())?;
+  diff.save(&diff_path).map_err(|e| e.to_string())?;
+  Err(format!(
+    "{}: {}个像素({:.2}%)超出容差，见 {:?}",
+    name,
+    mismatch,
+    ratio * 100.0,
+    diff_path
+  ))
+}
+
+#[test]
+fn scenes_match_golden_images() {
+  let options = Options {
+    headless: true,
+    width: WIDTH,
+    height: HEIGHT,
+    ..Default::default()
+  };
+  let context = crate::init_sdl(&options).and_then(|(sdl, video)| {
+    let window = crate::create_window(&video, &options)?;
+    Ok((sdl, window))
+  });
+  let (_sdl, _window) = match context {
+    Ok(context) => context,
+    Err(e) if std::env::var_os("GOLDEN_REQUIRED").is_none() => {
+      eprintln!("无法创建GL上下文，跳过参考图像测试: {:?}", e);
+      return;
+    }
+    Err(e) => panic!("无法创建GL上下文: {:?}", e),
+  };
+
+  let res = Resources::from_path(manifest_dir().join("assets"));
+  let golden_dir = manifest_dir().join("tests").join("golden");
+  let output_dir = manifest_dir().join("target").join("golden");
+  std::fs::create_dir_all(&golden_dir).unwrap();
+
+  time::set_fixed(Some(FIXED_TIME));
+  time::update();
+  Viewport::for_window(WIDTH as i32, HEIGHT as i32).refresh();
+  let camera_uniform = CameraUniform::new().unwrap();
+  let offscreen = OffScreen::new(&res, WIDTH as i32, HEIGHT as i32).unwrap();
+  let skybox = Skybox::from_res(&res, SKYBOX).unwrap();
+  let environment = Environment::from_res(&res, ENVIRONMENT).unwrap();
+
+  let mut failures = Vec::new();
+  for mut scene in scene::load_scenes(&res).unwrap() {
+    if let Err(e) = scene::render_frame(
+      &mut *scene,
+      &offscreen,
+      &camera_uniform,
+      &environment,
+      Some(&skybox),
+      WIDTH as f32 / HEIGHT as f32,
+    ) {
+      failures.push(format!("{}: 渲染失败 {:?}", scene.get_name(), e));
+      continue;
+    }
+    let actual = offscreen.read_pixels();
+    if let Err(e) = check(&scene.get_name(), &actual, &golden_dir, &output_dir) {
+      failures.push(e);
+    }
+  }
+  assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,61 @@
+use std::time::Instant;
+
+use glow::HasContext;
+
+use crate::cli::Options;
+use crate::geom::camera::CameraUniform;
+use crate::recorder::Recorder;
+use crate::render_gl::ibl::Environment;
+use crate::render_gl::offscreen::OffScreen;
+use crate::render_gl::skybox::Skybox;
+use crate::scene::{self, scene::Scene};
+use crate::{time, GL};
+
+/// 无窗口模式：将场景渲染到离屏帧缓冲，共options.frames帧
+pub fn run(
+  scene: &mut dyn Scene,
+  offscreen: &OffScreen,
+  camera_uniform: &CameraUniform,
+  environment: &Environment,
+  skybox: &Skybox,
+  options: &Options,
+) -> Result<(), anyhow::Error> {
+  time::set_fixed(options.time);
+  let mut recorder = match options.record {
+    Some(format) => Some(Recorder::start(format, options.fps)?),
+    None => None,
+  };
+  let start = Instant::now();
+  for _ in 0..options.frames {
+    if let Some(recorder) = &recorder {
+      recorder.advance_time();
+    }
+    time::update();
+    scene::render_frame(
+      scene,
+      offscreen,
+      camera_uniform,
+      environment,
+      Some(skybox),
+      options.width as f32 / options.height as f32,
+    )?;
+    if let Some(recorder) = &mut recorder {
+      recorder.capture(&offscreen.read_pixels())?;
+    }
+  }
+  if let Some(recorder) = recorder {
+    println!("录制已保存 {:?}", recorder.finish()?);
+  }
+  unsafe {
+    GL.finish();
+  }
+  println!(
+    "已渲染场景 {} 共{}帧 ({}x{})，用时 {:.2}s",
+    scene.get_name(),
+    options.frames,
+    options.width,
+    options.height,
+    start.elapsed().as_secs_f32()
+  );
+  Ok(())
+}
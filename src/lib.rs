@@ -38,3 +38,11 @@ pub unsafe fn any_as_u8_slice<T: Sized>(p: &[T]) -> &[u8] {
     std::mem::size_of::<T>() * p.len(),
   )
 }
+
+/// 用于从GL读回数据
+/// # Safety
+/// T的任意字节组合都须是合法的值
+pub unsafe fn any_as_u8_slice_mut<T: Sized>(p: &mut [T]) -> &mut [u8] {
+  let len = std::mem::size_of_val(p);
+  std::slice::from_raw_parts_mut((p as *mut [T]) as *mut u8, len)
+}
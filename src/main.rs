@@ -1,18 +1,21 @@
 extern crate egui_sdl2_gl as egui_backend;
 extern crate nalgebra as na;
 
+use crate::geom::camera::CameraUniform;
 use crate::scene::scene::Scene;
-use crate::scene::spin;
 use another::LateInit;
 use anyhow::anyhow;
 use egui_backend::{DpiScaling, ShaderVersion};
 use glow::HasContext;
 use na::Vector3;
 
+use render_gl::ibl::Environment;
 use render_gl::offscreen::OffScreen;
+use render_gl::skybox::Skybox;
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
-use sdl2::video::{GLProfile, SwapInterval};
+use sdl2::video::{GLContext, GLProfile, SwapInterval, Window};
+use sdl2::{Sdl, VideoSubsystem};
 use std::ops::DerefMut;
 use std::path::Path;
 use std::sync::RwLock;
@@ -21,89 +24,159 @@ use std::time::Instant;
 #[macro_use]
 extern crate render_gl_derive;
 
+use crate::cli::Options;
 use crate::fonts::install_fonts;
+use crate::recorder::{Format, Recorder};
 use crate::resources::Resources;
+use crate::screenshot::Capture;
 
+mod cli;
 pub mod fonts;
 pub mod geom;
+#[cfg(test)]
+mod golden;
+mod headless;
 pub mod input;
+pub mod model;
+mod recorder;
 pub mod render_gl;
 pub mod resources;
 pub mod scene;
+mod screenshot;
 mod time;
 
 static GL: LateInit<glow::Context> = LateInit::new();
- 
-fn main() -> Result<(), anyhow::Error> {
-  let mut screen_width = 1920;
-  let mut screen_height = 1200;
+// 所有场景共用的天空盒
+const SKYBOX: &str = "textures/skybox";
+// 等距柱状投影的HDR全景图，预计算环境光照
+const ENVIRONMENT: &str = "textures/sky.hdr";
 
-  let res = Resources::from_relative_exe_path(Path::new("assets"))?;
+/// 初始化SDL与视频子系统，无窗口模式下默认使用offscreen视频驱动
+fn init_sdl(options: &Options) -> Result<(Sdl, VideoSubsystem), anyhow::Error> {
+  if options.headless && std::env::var_os("SDL_VIDEODRIVER").is_none() {
+    // 无需显示服务器，通过EGL创建上下文(可配合Mesa llvmpipe使用)
+    sdl2::hint::set("SDL_VIDEODRIVER", "offscreen");
+  }
   let sdl_context = sdl2::init().map_err(|msg| anyhow!("Sdl2 初始化失败 {}", msg))?;
   let video_subsystem = sdl_context
     .video()
     .map_err(|msg| anyhow!("视频子系统获取失败 {}", msg))?;
+  Ok((sdl_context, video_subsystem))
+}
 
-  let mouse = sdl_context.mouse();
-
+/// 创建窗口与GL上下文，初始化全局的GL及默认的渲染状态
+fn create_window(
+  video_subsystem: &VideoSubsystem,
+  options: &Options,
+) -> Result<(Window, GLContext), anyhow::Error> {
   let gl_attr = video_subsystem.gl_attr();
   gl_attr.set_context_profile(GLProfile::Core);
   // egui支持下限为320
   gl_attr.set_context_version(4, 5);
 
-  let window = video_subsystem
-    .window("Another (SDL2 + OpenGL后端)", screen_width, screen_height)
-    .resizable()
-    .position_centered()
-    .opengl()
-    .build()?;
+  let mut window_builder =
+    video_subsystem.window("Another (SDL2 + OpenGL后端)", options.width, options.height);
+  window_builder.opengl();
+  if options.headless {
+    window_builder.hidden();
+  } else {
+    window_builder.resizable().position_centered();
+  }
+  let window = window_builder.build()?;
 
-  let _ctx = window
+  let ctx = window
     .gl_create_context()
     .map_err(|msg| anyhow!("创建GL上下文失败: {}", msg))?;
 
-  let shader_ver = ShaderVersion::Adaptive;
-  let (mut painter, mut egui_state) =
-    egui_backend::with_sdl2(&window, shader_ver, DpiScaling::Custom(1.5)); // UI缩放，将影响<设备像素密度>
-  let mut egui_ctx = egui::CtxRef::default();
-  // 安装中文字体
-  install_fonts(&egui_ctx);
   GL.init(unsafe {
     glow::Context::from_loader_function(|s| video_subsystem.gl_get_proc_address(s) as *const _)
   });
-
-  // 获取<事件泵>，这是在SDL2中处理事件的传统方式
-  let mut event_pump = sdl_context
-    .event_pump()
-    .map_err(|msg| anyhow!("事件泵获取失败: {}", msg))?;
-
-  let mut viewport = render_gl::Viewport::for_window(screen_width as i32, screen_height as i32);
-  viewport.refresh();
+  render_gl::sampler::init();
 
   let color_buffer = render_gl::ColorBuffer::from_color(Vector3::new(0.0, 0.0, 0.0));
   color_buffer.clear();
+  unsafe {
+    GL.enable(glow::BLEND);
+    // 立方体贴图在面与面之间过滤，消除天空盒的接缝
+    GL.enable(glow::TEXTURE_CUBE_MAP_SEAMLESS);
+  }
+  Ok((window, ctx))
+}
 
-  let mut scene_manager: Vec<RwLock<Box<dyn Scene>>> = Vec::new();
-  scene_manager.push(RwLock::new(Box::new(spin::Cube::new(&res)?)));
+fn main() -> Result<(), anyhow::Error> {
+  let options = Options::from_args()?;
+  let mut screen_width = options.width;
+  let mut screen_height = options.height;
+
+  let res = Resources::from_relative_exe_path(Path::new("assets"))?;
+  let (sdl_context, video_subsystem) = init_sdl(&options)?;
 
-  scene_manager.push(RwLock::new(Box::new(scene::cube::Cube2::new(&res)?)));
+  let mouse = sdl_context.mouse();
+
+  let (window, _ctx) = create_window(&video_subsystem, &options)?;
+
+  let mut viewport = render_gl::Viewport::for_window(screen_width as i32, screen_height as i32);
+  viewport.refresh();
 
-  scene_manager.push(RwLock::new(Box::new(scene::phong::Cube::new(&res)?)));
+  let scenes = scene::load_scenes(&res)?;
+  let mut scene_index = match &options.scene {
+    Some(key) => scene::find_scene(&scenes, key).ok_or_else(|| anyhow!("找不到场景 {}", key))?,
+    None => 0,
+  };
+  let scene_manager: Vec<RwLock<Box<dyn Scene>>> = scenes.into_iter().map(RwLock::new).collect();
 
   render_gl::debug::check_error();
-  let mut scene_index = 0;
 
   let mut quit = false;
   let mut input_enable = false;
   let mut vsync = true;
+  let mut skybox_enable = true;
+  // 下一帧需要保存的截图
+  let mut capture: Option<Capture> = None;
+  let mut recorder: Option<Recorder> = None;
+  let mut record_format = Format::Png;
+  let mut record_fps = 60;
+  let mut toggle_recording = false;
+
+  let camera_uniform = CameraUniform::new()?;
 
   // todo
   let offscreen = OffScreen::new(&res, screen_width as i32, screen_height as i32)?;
+  let skybox = Skybox::from_res(&res, SKYBOX)?;
+  let environment = Environment::from_res(&res, ENVIRONMENT)?;
 
   time::update();
-  unsafe {
-    GL.enable(glow::BLEND);
+
+  if options.headless {
+    let mut scene = scene_manager[scene_index].write().unwrap();
+    return headless::run(
+      &mut **scene,
+      &offscreen,
+      &camera_uniform,
+      &environment,
+      &skybox,
+      &options,
+    );
   }
+  // 多重采样抗锯齿，仅窗口模式默认开启
+  let max_samples = render_gl::frame_buffer::max_samples().max(1);
+  let mut msaa = 4.min(max_samples);
+  // 帧缓冲创建失败时保留原来的采样数，并在界面中显示原因
+  let mut msaa_error = offscreen.set_samples(msaa).err();
+  msaa = offscreen.samples();
+
+  let shader_ver = ShaderVersion::Adaptive;
+  let (mut painter, mut egui_state) =
+    egui_backend::with_sdl2(&window, shader_ver, DpiScaling::Custom(1.5)); // UI缩放，将影响<设备像素密度>
+  let mut egui_ctx = egui::CtxRef::default();
+  // 安装中文字体
+  install_fonts(&egui_ctx);
+
+  // 获取<事件泵>，这是在SDL2中处理事件的传统方式
+  let mut event_pump = sdl_context
+    .event_pump()
+    .map_err(|msg| anyhow!("事件泵获取失败: {}", msg))?;
+
   let start_time = Instant::now();
   'running: loop {
     window
@@ -120,7 +193,11 @@ fn main() -> Result<(), anyhow::Error> {
 
     viewport.refresh();
     // 自定义的OpenGL渲染部分
+    if let Some(recorder) = &recorder {
+      recorder.advance_time();
+    }
     time::update();
+    render_gl::reload::poll();
     let mut scene_rwlock = match scene_manager.get(scene_index) {
       Some(scene) => scene.write().unwrap(),
       None => scene_manager.get(0).unwrap().write().unwrap(),
@@ -131,15 +208,26 @@ fn main() -> Result<(), anyhow::Error> {
     } else {
       let _ = input::fetch_motion();
     }
-    offscreen.bind();
-    unsafe {
-      GL.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
-      GL.enable(glow::DEPTH_TEST);
+    // 如严格模式下上传了不存在的uniform，在界面中显示
+    let render_error = scene::render_frame(
+      &mut **scene,
+      &offscreen,
+      &camera_uniform,
+      &environment,
+      skybox_enable.then_some(&skybox),
+      screen_width as f32 / screen_height as f32,
+    )
+    .err();
+    if let Some(active) = &mut recorder {
+      let image = offscreen.read_pixels();
+      if let Err(e) = active.capture(&image) {
+        println!("{:?}", e);
+        toggle_recording = true;
+      }
     }
-    scene.render(screen_width as f32 / screen_height as f32);
-    offscreen.detach();
-    unsafe {
-      GL.disable(glow::DEPTH_TEST);
+    if capture == Some(Capture::Scene) {
+      let image = offscreen.read_pixels();
+      save_screenshot(&image, Capture::Scene);
     }
     offscreen.render_output();
 
@@ -147,7 +235,57 @@ fn main() -> Result<(), anyhow::Error> {
     egui::Window::new("Egui 主窗口").show(&egui_ctx, |ui| {
       ui.label("使用LCtrl进入/退出摄像机模式");
       ui.label(format!("FPS: {}", (1.0 / time::get_delta()) as i32));
-      ui.checkbox(&mut vsync, "垂直同步").clicked();
+      ui.horizontal(|ui| {
+        ui.checkbox(&mut vsync, "垂直同步").clicked();
+        egui::ComboBox::from_label("抗锯齿")
+          .selected_text(msaa_name(msaa))
+          .show_ui(ui, |ui| {
+            for samples in [1, 2, 4, 8] {
+              if samples <= max_samples {
+                ui.selectable_value(&mut msaa, samples, msaa_name(samples));
+              }
+            }
+          });
+      });
+      if let Some(error) = &msaa_error {
+        ui.colored_label(egui::Color32::RED, format!("抗锯齿切换失败: {:?}", error));
+      }
+      ui.checkbox(&mut skybox_enable, "天空盒");
+      let mut ibl_enable = environment.enabled();
+      if ui.checkbox(&mut ibl_enable, "环境光照(IBL)").changed() {
+        environment.set_enabled(ibl_enable);
+      }
+      let mut intensity = environment.intensity();
+      if ui
+        .add(egui::Slider::new(&mut intensity, 0.0..=2.0).text("环境光强度"))
+        .changed()
+      {
+        environment.set_intensity(intensity);
+      }
+      ui.label("F12 截取场景，Shift+F12 截取含界面的画面");
+      ui.separator();
+      match &recorder {
+        Some(active) => {
+          ui.label(format!(
+            "录制中 {} 帧 {:?}",
+            active.frame_count(),
+            active.path()
+          ));
+          if ui.button("停止录制 (F9)").clicked() {
+            toggle_recording = true;
+          }
+        }
+        None => {
+          ui.horizontal(|ui| {
+            ui.radio_value(&mut record_format, Format::Png, "PNG序列");
+            ui.radio_value(&mut record_format, Format::Y4m, "Y4M视频");
+          });
+          ui.add(egui::Slider::new(&mut record_fps, 1..=120).text("录制帧率"));
+          if ui.button("开始录制 (F9)").clicked() {
+            toggle_recording = true;
+          }
+        }
+      }
       ui.separator();
       ui.label(format!("视窗变换 宽 {} 高 {}", viewport.w, viewport.h));
       ui.label(format!(
@@ -160,6 +298,10 @@ fn main() -> Result<(), anyhow::Error> {
         quit = true;
       }
     });
+    if msaa != offscreen.samples() {
+      msaa_error = offscreen.set_samples(msaa).err();
+      msaa = offscreen.samples();
+    }
     egui::Window::new("场景轮换指示器")
       .resizable(false)
       .show(&egui_ctx, |ui| {
@@ -168,6 +310,32 @@ fn main() -> Result<(), anyhow::Error> {
         ui.label(format!("场景名称 {}", scene.get_name()));
       });
     scene.render_window(&egui_ctx);
+    egui::Window::new("后处理")
+      .resizable(false)
+      .show(&egui_ctx, |ui| {
+        offscreen.bloom.write().unwrap().edit(ui);
+        ui.separator();
+        ui.label("色调映射");
+        offscreen.tonemap.write().unwrap().edit(ui);
+        ui.separator();
+        offscreen.post.write().unwrap().edit(ui);
+      });
+    if let Some(error) = &render_error {
+      egui::Window::new("渲染失败").show(&egui_ctx, |ui| {
+        ui.colored_label(egui::Color32::RED, format!("{:?}", error));
+      });
+    }
+    let shader_errors = render_gl::reload::errors();
+    if !shader_errors.is_empty() {
+      egui::Window::new("着色器重载失败").show(&egui_ctx, |ui| {
+        ui.label("已保留旧的着色程序，修正源文件后将自动重试");
+        for (name, error) in shader_errors {
+          ui.separator();
+          ui.label(name);
+          ui.colored_label(egui::Color32::RED, error);
+        }
+      });
+    }
 
     // egui前端完成渲染，生成后端无关的<绘制指令>
     let (egui_output, paint_cmds) = egui_ctx.end_frame();
@@ -176,6 +344,11 @@ fn main() -> Result<(), anyhow::Error> {
     let paint_jobs = egui_ctx.tessellate(paint_cmds);
     // 由egui后端完成实际的绘制
     painter.paint_jobs(None, paint_jobs, &egui_ctx.font_image());
+    if capture.take() == Some(Capture::WithUi) {
+      let (width, height) = window.drawable_size();
+      let image = render_gl::frame_buffer::read_screen(width as i32, height as i32);
+      save_screenshot(&image, Capture::WithUi);
+    }
     // 用OpenGL渲染结果更新窗口
     window.gl_swap_window();
     drop(scene_rwlock);
@@ -223,6 +396,35 @@ fn main() -> Result<(), anyhow::Error> {
         }
       }
     }
+    if input::get_key_with_cooldown(Keycode::F9, 0.5) {
+      toggle_recording = true;
+    }
+    if toggle_recording {
+      toggle_recording = false;
+      recorder = match recorder.take() {
+        Some(active) => {
+          match active.finish() {
+            Ok(path) => println!("录制已保存 {:?}", path),
+            Err(e) => println!("{:?}", e),
+          }
+          None
+        }
+        None => match Recorder::start(record_format, record_fps) {
+          Ok(recorder) => Some(recorder),
+          Err(e) => {
+            println!("{:?}", e);
+            None
+          }
+        },
+      };
+    }
+    if input::get_key_with_cooldown(Keycode::F12, 0.5) {
+      capture = if input::get_key(Keycode::LShift) || input::get_key(Keycode::RShift) {
+        Some(Capture::WithUi)
+      } else {
+        Some(Capture::Scene)
+      };
+    }
     if input::get_key_with_cooldown(Keycode::LCtrl, 0.2) {
       input_enable = !input_enable;
       mouse.set_relative_mouse_mode(input_enable);
@@ -230,3 +432,18 @@ fn main() -> Result<(), anyhow::Error> {
   }
   Ok(())
 }
+
+fn msaa_name(samples: i32) -> String {
+  if samples > 1 {
+    format!("MSAA {}x", samples)
+  } else {
+    "关闭".to_string()
+  }
+}
+
+fn save_screenshot(image: &image::RgbaImage, capture: Capture) {
+  match screenshot::save(image, capture) {
+    Ok(path) => println!("截图已保存 {:?}", path),
+    Err(e) => println!("{:?}", e),
+  }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use image::DynamicImage;
+use na::Matrix4;
+
+use super::{Error, Material, Model, ModelMesh, Vertex};
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture::Texture;
+use crate::resources::Resources;
+
+/// 加载.gltf/.glb模型，name为相对资源根目录的路径，如 "models/box/box.gltf"
+pub fn load(res: &Resources, name: &str) -> Result<Model, Error> {
+  let (document, buffers, images) =
+    ::gltf::import(res.resolve_path(name)).map_err(|e| Error::Gltf {
+      name: name.to_string(),
+      inner: e,
+    })?;
+
+  // 只有基础色与自发光纹理按sRGB解码，法线、金属度/粗糙度、遮蔽等保持线性
+  let color_images: HashSet<usize> = document
+    .materials()
+    .flat_map(|material| {
+      [
+        material
+          .pbr_metallic_roughness()
+          .base_color_texture()
+          .map(|info| info.texture().source().index()),
+        material
+          .emissive_texture()
+          .map(|info| info.texture().source().index()),
+      ]
+    })
+    .flatten()
+    .collect();
+  let textures = images
+    .into_iter()
+    .enumerate()
+    .map(|(index, data)| {
+      let sampler = if color_images.contains(&index) {
+        SamplerDesc::srgb()
+      } else {
+        SamplerDesc::new()
+      };
+      Ok(Texture::from_image_with(to_dynamic_image(data)?, &sampler)?)
+    })
+    .collect::<Result<Vec<Texture>, Error>>()?;
+
+  let materials = document
+    .materials()
+    .map(|material| {
+      let pbr = material.pbr_metallic_roughness();
+      Material {
+        name: material.name().unwrap_or_default().to_string(),
+        base_color: pbr.base_color_factor().into(),
+        base_color_texture: pbr
+          .base_color_texture()
+          .map(|info| info.texture().source().index()),
+      }
+    })
+    .collect();
+
+  let mut meshes = Vec::new();
+  let scene = document
+    .default_scene()
+    .or_else(|| document.scenes().next());
+  if let Some(scene) = scene {
+    for node in scene.nodes() {
+      load_node(&node, &Matrix4::identity(), &buffers, &mut meshes)?;
+    }
+  }
+
+  Model::new(meshes, materials, textures)
+}
+
+fn load_node(
+  node: &::gltf::Node,
+  parent: &Matrix4<f32>,
+  buffers: &[::gltf::buffer::Data],
+  meshes: &mut Vec<ModelMesh>,
+) -> Result<(), Error> {
+  // glTF的矩阵按列存储，与nalgebra一致
+  let transform = parent * Matrix4::from(node.transform().matrix());
+  if let Some(mesh) = node.mesh() {
+    let mesh_name = mesh.name().unwrap_or_default();
+    for primitive in mesh.primitives() {
+      if primitive.mode() != ::gltf::mesh::Mode::Triangles {
+        continue;
+      }
+      let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+      let positions: Vec<[f32; 3]> = reader
+        .read_positions()
+        .ok_or_else(|| Error::MissingAttribute {
+          mesh: mesh_name.to_string(),
+          attribute: "POSITION".to_string(),
+        })?
+        .collect();
+      let normals: Vec<[f32; 3]> = match reader.read_normals() {
+        Some(normals) => normals.collect(),
+        None => vec![[0.0, 0.0, 0.0]; positions.len()],
+      };
+      let tex_coords: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+        Some(tex_coords) => tex_coords.into_f32().collect(),
+        None => vec![[0.0, 0.0]; positions.len()],
+      };
+      let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+      };
+
+      let vertices: Vec<Vertex> = positions
+        .iter()
+        .zip(normals.iter())
+        .zip(tex_coords.iter())
+        .map(|((pos, nor), tex)| Vertex {
+          pos: (pos[0], pos[1], pos[2]).into(),
+          // glTF的纹理坐标原点在图片左上角，与图片数据的行序一致，无需翻转
+          tex: (tex[0], tex[1]).into(),
+          nor: (nor[0], nor[1], nor[2]).into(),
+        })
+        .collect();
+
+      meshes.push(ModelMesh {
+        mesh: Mesh::new(&vertices, &indices, Vertex::vertex_attrib_pointers),
+        material: primitive.material().index(),
+        transform,
+      });
+    }
+  }
+  for child in node.children() {
+    load_node(&child, &transform, buffers, meshes)?;
+  }
+  Ok(())
+}
+
+fn to_dynamic_image(data: ::gltf::image::Data) -> Result<DynamicImage, Error> {
+  use ::gltf::image::Format;
+  let (width, height) = (data.width, data.height);
+  let format = data.format;
+  // 16位的通道以本机字节序存储
+  let to_u16 = |pixels: Vec<u8>| -> Vec<u16> {
+    pixels
+      .chunks_exact(2)
+      .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+      .collect()
+  };
+  let img = match format {
+    Format::R8 => {
+      image::GrayImage::from_raw(width, height, data.pixels).map(DynamicImage::ImageLuma8)
+    }
+    Format::R8G8 => {
+      image::GrayAlphaImage::from_raw(width, height, data.pixels).map(DynamicImage::ImageLumaA8)
+    }
+    Format::R8G8B8 => {
+      image::RgbImage::from_raw(width, height, data.pixels).map(DynamicImage::ImageRgb8)
+    }
+    Format::R8G8B8A8 => {
+      image::RgbaImage::from_raw(width, height, data.pixels).map(DynamicImage::ImageRgba8)
+    }
+    Format::R16 => image::ImageBuffer::from_raw(width, height, to_u16(data.pixels))
+      .map(DynamicImage::ImageLuma16),
+    Format::R16G16 => image::ImageBuffer::from_raw(width, height, to_u16(data.pixels))
+      .map(DynamicImage::ImageLumaA16),
+    Format::R16G16B16 => {
+      image::ImageBuffer::from_raw(width, height, to_u16(data.pixels)).map(DynamicImage::ImageRgb16)
+    }
+    Format::R16G16B16A16 => image::ImageBuffer::from_raw(width, height, to_u16(data.pixels))
+      .map(DynamicImage::ImageRgba16),
+    #[allow(unreachable_patterns)]
+    _ => None,
+  };
+  img.ok_or_else(|| Error::UnsupportedImageFormat(format!("{:?}", format)))
+}
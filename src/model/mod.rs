@@ -0,0 +1,161 @@
+use glow::HasContext;
+use na::{Matrix3, Matrix4, Vector4};
+use thiserror::Error;
+
+use crate::render_gl::data::*;
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::{self, texture, Program};
+use crate::resources::Resources;
+use crate::GL;
+
+pub mod gltf;
+pub mod obj;
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("glTF 解析失败 {name}")]
+  Gltf {
+    name: String,
+    #[source]
+    inner: ::gltf::Error,
+  },
+  #[error("OBJ 解析失败 {name}")]
+  Obj {
+    name: String,
+    #[source]
+    inner: tobj::LoadError,
+  },
+  #[error("MTL 材质解析失败 {name}")]
+  Mtl {
+    name: String,
+    #[source]
+    inner: tobj::LoadError,
+  },
+  #[error("纹理加载失败")]
+  Texture(#[from] texture::Error),
+  #[error("图元 {mesh} 缺失顶点属性 {attribute}")]
+  MissingAttribute { mesh: String, attribute: String },
+  #[error("无法根据扩展名判断模型格式 {0}")]
+  UnknownModelFormat(String),
+  #[error("不支持的图片格式 {0}")]
+  UnsupportedImageFormat(String),
+}
+
+/// 根据扩展名选择 glTF 或 OBJ 加载器
+pub fn load(res: &Resources, name: &str) -> Result<Model, Error> {
+  let ext = name.rsplit('.').next().unwrap_or_default();
+  match ext.to_ascii_lowercase().as_str() {
+    "gltf" | "glb" => gltf::load(res, name),
+    "obj" => obj::load(res, name),
+    _ => Err(Error::UnknownModelFormat(name.to_string())),
+  }
+}
+
+/// 与phong着色器布局一致的交错顶点
+#[derive(VertexAttribPointers, Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct Vertex {
+  #[location = 0]
+  pub pos: f32_f32_f32,
+  #[location = 1]
+  pub tex: f32_f32,
+  #[location = 2]
+  pub nor: f32_f32_f32,
+}
+
+pub struct Material {
+  pub name: String,
+  pub base_color: Vector4<f32>,
+  // Model::textures 中的下标
+  pub base_color_texture: Option<usize>,
+}
+impl Default for Material {
+  /// 白色且无纹理
+  fn default() -> Self {
+    Material {
+      name: String::from("default"),
+      base_color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+      base_color_texture: None,
+    }
+  }
+}
+
+pub struct ModelMesh {
+  pub mesh: Mesh,
+  // Model::materials 中的下标
+  pub material: Option<usize>,
+  // 节点层级累乘得到的模型空间变换
+  pub transform: Matrix4<f32>,
+}
+
+pub struct Model {
+  pub meshes: Vec<ModelMesh>,
+  pub materials: Vec<Material>,
+  pub textures: Vec<texture::Texture>,
+  // 无纹理材质使用的1x1白色纹理
+  white: texture::Texture,
+}
+
+impl Model {
+  pub fn new(
+    meshes: Vec<ModelMesh>,
+    materials: Vec<Material>,
+    textures: Vec<texture::Texture>,
+  ) -> Result<Model, Error> {
+    Ok(Model {
+      meshes,
+      materials,
+      textures,
+      white: texture::Texture::white()?,
+    })
+  }
+
+  /// 以给定纹理替换所有材质的基础色纹理
+  pub fn set_texture(&mut self, texture: texture::Texture) {
+    self.textures.push(texture);
+    let index = self.textures.len() - 1;
+    if self.materials.is_empty() {
+      self.materials.push(Material::default());
+    }
+    for mesh in &mut self.meshes {
+      mesh.material.get_or_insert(0);
+    }
+    for material in &mut self.materials {
+      material.base_color_texture = Some(index);
+    }
+  }
+
+  /// 依次绘制所有网格，材质纹理绑定到纹理单元0
+  pub fn render(
+    &self,
+    program: &Program,
+    model_mat: &Matrix4<f32>,
+  ) -> Result<(), render_gl::Error> {
+    program.set_used();
+    for mesh in &self.meshes {
+      let world = model_mat * mesh.transform;
+      let nor_mat: Matrix3<f32> = world
+        .fixed_resize::<3, 3>(0.0)
+        .try_inverse()
+        .unwrap_or_else(Matrix3::identity)
+        .transpose();
+      let material = mesh.material.and_then(|i| self.materials.get(i));
+      let base_color = material
+        .map(|m| m.base_color)
+        .unwrap_or_else(|| Vector4::new(1.0, 1.0, 1.0, 1.0));
+      let texture = material
+        .and_then(|m| m.base_color_texture)
+        .and_then(|i| self.textures.get(i))
+        .unwrap_or(&self.white);
+      unsafe {
+        GL.active_texture(glow::TEXTURE0);
+      }
+      texture.bind();
+      program.upload_mat4("m_proj", &world)?;
+      program.upload_mat3("NormalMat", &nor_mat)?;
+      program.upload_vec4("baseColor", &base_color)?;
+      mesh.mesh.draw();
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use na::{Matrix4, Vector3, Vector4};
+
+use super::{Error, Material, Model, ModelMesh, Vertex};
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture::Texture;
+use crate::resources::Resources;
+
+/// 加载Wavefront .obj模型及其引用的.mtl材质
+/// name为相对资源根目录的路径，如 "models/box/box.obj"
+pub fn load(res: &Resources, name: &str) -> Result<Model, Error> {
+  let options = tobj::LoadOptions {
+    // 将多边形面拆分为三角形
+    triangulate: true,
+    // 合并相同的 位置/纹理坐标/法线 组合，得到单一索引
+    single_index: true,
+    ..Default::default()
+  };
+  let (models, materials) =
+    tobj::load_obj(res.resolve_path(name), &options).map_err(|e| Error::Obj {
+      name: name.to_string(),
+      inner: e,
+    })?;
+  // 缺失的.mtl不影响几何体的加载，改用默认材质；格式错误则作为错误返回
+  let (obj_materials, fallback) = match materials {
+    Ok(materials) => (materials, None),
+    Err(tobj::LoadError::OpenFileFailed) => {
+      eprintln!("警告: 模型 {} 的材质文件无法打开，使用默认材质", name);
+      (Vec::new(), Some(0))
+    }
+    Err(e) => {
+      return Err(Error::Mtl {
+        name: name.to_string(),
+        inner: e,
+      })
+    }
+  };
+
+  // 纹理路径相对于.obj所在目录
+  let dir = match name.rfind('/') {
+    Some(i) => &name[..=i],
+    None => "",
+  };
+  let mut textures = Vec::new();
+  let mut texture_index: HashMap<String, usize> = HashMap::new();
+  let mut materials = Vec::with_capacity(obj_materials.len());
+  for material in obj_materials {
+    let base_color_texture = if material.diffuse_texture.is_empty() {
+      None
+    } else {
+      let texture_name = format!("{}{}", dir, material.diffuse_texture.replace('\\', "/"));
+      let index = match texture_index.get(&texture_name) {
+        Some(index) => *index,
+        None => {
+          textures.push(Texture::from_res_with(
+            res,
+            &texture_name,
+            &SamplerDesc::srgb(),
+          )?);
+          texture_index.insert(texture_name, textures.len() - 1);
+          textures.len() - 1
+        }
+      };
+      Some(index)
+    };
+    let [r, g, b] = material.diffuse;
+    materials.push(Material {
+      name: material.name,
+      base_color: Vector4::new(r, g, b, material.dissolve),
+      base_color_texture,
+    });
+  }
+  if fallback.is_some() {
+    materials.push(Material::default());
+  }
+
+  let meshes = models
+    .iter()
+    .map(|model| {
+      let mesh = &model.mesh;
+      let vertices = to_vertices(mesh);
+      ModelMesh {
+        mesh: Mesh::new(&vertices, &mesh.indices, Vertex::vertex_attrib_pointers),
+        material: mesh.material_id.or(fallback),
+        transform: Matrix4::identity(),
+      }
+    })
+    .collect();
+
+  Model::new(meshes, materials, textures)
+}
+
+fn to_vertices(mesh: &tobj::Mesh) -> Vec<Vertex> {
+  let count = mesh.positions.len() / 3;
+  let normals = if mesh.normals.len() == count * 3 {
+    mesh.normals.clone()
+  } else {
+    smooth_normals(&mesh.positions, &mesh.indices)
+  };
+  (0..count)
+    .map(|i| {
+      let tex = if mesh.texcoords.len() >= (i + 1) * 2 {
+        // OBJ的纹理坐标原点在图片左下角
+        (mesh.texcoords[i * 2], 1.0 - mesh.texcoords[i * 2 + 1])
+      } else {
+        (0.0, 0.0)
+      };
+      Vertex {
+        pos: (
+          mesh.positions[i * 3],
+          mesh.positions[i * 3 + 1],
+          mesh.positions[i * 3 + 2],
+        )
+          .into(),
+        tex: tex.into(),
+        nor: (normals[i * 3], normals[i * 3 + 1], normals[i * 3 + 2]).into(),
+      }
+    })
+    .collect()
+}
+
+/// 文件未提供法线时，累加相邻三角形的面法线
+fn smooth_normals(positions: &[f32], indices: &[u32]) -> Vec<f32> {
+  let position = |i: u32| {
+    let i = i as usize * 3;
+    Vector3::new(positions[i], positions[i + 1], positions[i + 2])
+  };
+  let mut normals = vec![Vector3::<f32>::zeros(); positions.len() / 3];
+  for face in indices.chunks_exact(3) {
+    let (a, b, c) = (position(face[0]), position(face[1]), position(face[2]));
+    // 未归一化的叉积，面积越大的三角形权重越大
+    let normal = (b - a).cross(&(c - a));
+    for i in face {
+      normals[*i as usize] += normal;
+    }
+  }
+  normals
+    .iter()
+    .flat_map(|n| {
+      let n = n.try_normalize(f32::EPSILON).unwrap_or_else(Vector3::zeros);
+      [n.x, n.y, n.z]
+    })
+    .collect()
+}
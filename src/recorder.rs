@@ -0,0 +1,146 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use image::RgbaImage;
+
+use crate::time;
+
+// 录制输出目录，相对于工作目录
+const RECORDING_DIR: &str = "recordings";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+  // 编号的PNG序列 frame-00000.png ...
+  Png,
+  // YUV4MPEG2(4:4:4)视频流，可由 ffmpeg -i xxx.y4m 读取
+  Y4m,
+}
+impl Format {
+  pub fn parse(name: &str) -> Option<Format> {
+    match name {
+      "png" => Some(Format::Png),
+      "y4m" => Some(Format::Y4m),
+      _ => None,
+    }
+  }
+}
+
+/// 以固定的模拟时间步长逐帧录制离屏渲染结果，与实际帧率无关
+pub struct Recorder {
+  format: Format,
+  fps: u32,
+  // 开始录制时的场景时间
+  start: f32,
+  frame: u32,
+  path: PathBuf,
+  y4m: Option<(BufWriter<File>, u32, u32)>,
+}
+
+impl Recorder {
+  pub fn start(format: Format, fps: u32) -> Result<Recorder, anyhow::Error> {
+    let millis = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_millis())
+      .unwrap_or_default();
+    std::fs::create_dir_all(RECORDING_DIR).context("录制目录创建失败")?;
+    let path = match format {
+      Format::Png => PathBuf::from(RECORDING_DIR).join(format!("recording-{}", millis)),
+      Format::Y4m => PathBuf::from(RECORDING_DIR).join(format!("recording-{}.y4m", millis)),
+    };
+    if format == Format::Png {
+      std::fs::create_dir_all(&path).with_context(|| format!("录制目录创建失败 {:?}", path))?;
+    }
+    Ok(Recorder {
+      format,
+      fps: fps.max(1),
+      start: time::get_now(),
+      frame: 0,
+      path,
+      y4m: None,
+    })
+  }
+
+  pub fn frame_count(&self) -> u32 {
+    self.frame
+  }
+  pub fn path(&self) -> &PathBuf {
+    &self.path
+  }
+
+  /// 将场景时间固定为当前帧的时刻，需在time::update之前调用
+  pub fn advance_time(&self) {
+    time::set_fixed(Some(self.start + self.frame as f32 / self.fps as f32));
+  }
+
+  /// 写入一帧
+  pub fn capture(&mut self, image: &RgbaImage) -> Result<(), anyhow::Error> {
+    match self.format {
+      Format::Png => {
+        let path = self.path.join(format!("frame-{:05}.png", self.frame));
+        image
+          .save(&path)
+          .with_context(|| format!("帧保存失败 {:?}", path))?;
+      }
+      Format::Y4m => self.write_y4m(image)?,
+    }
+    self.frame += 1;
+    Ok(())
+  }
+
+  fn write_y4m(&mut self, image: &RgbaImage) -> Result<(), anyhow::Error> {
+    let (width, height) = image.dimensions();
+    if self.y4m.is_none() {
+      let mut writer = BufWriter::new(
+        File::create(&self.path).with_context(|| format!("视频文件创建失败 {:?}", self.path))?,
+      );
+      writeln!(
+        writer,
+        "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+        width, height, self.fps
+      )?;
+      self.y4m = Some((writer, width, height));
+    }
+    let (writer, w, h) = self.y4m.as_mut().unwrap();
+    if (*w, *h) != (width, height) {
+      return Err(anyhow!(
+        "Y4M录制中帧尺寸不能改变 {}x{} -> {}x{}",
+        w,
+        h,
+        width,
+        height
+      ));
+    }
+    // BT.601 有限范围，按平面依次写入Y、U、V
+    let pixels = image
+      .pixels()
+      .map(|p| (p[0] as i32, p[1] as i32, p[2] as i32));
+    let y: Vec<u8> = pixels
+      .clone()
+      .map(|(r, g, b)| (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8)
+      .collect();
+    let u: Vec<u8> = pixels
+      .clone()
+      .map(|(r, g, b)| (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8)
+      .collect();
+    let v: Vec<u8> = pixels
+      .map(|(r, g, b)| (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8)
+      .collect();
+    writer.write_all(b"FRAME\n")?;
+    writer.write_all(&y)?;
+    writer.write_all(&u)?;
+    writer.write_all(&v)?;
+    Ok(())
+  }
+
+  /// 结束录制并恢复真实时间，返回输出路径
+  pub fn finish(self) -> Result<PathBuf, anyhow::Error> {
+    time::set_fixed(None);
+    if let Some((mut writer, _, _)) = self.y4m {
+      writer.flush()?;
+    }
+    Ok(self.path)
+  }
+}
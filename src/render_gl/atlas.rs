@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use image::{DynamicImage, RgbaImage};
+use na::Vector2;
+use thiserror::Error;
+
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture::{self, load_image, Texture};
+use crate::resources::Resources;
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("图集超出最大尺寸 {max}x{max}")]
+  TooLarge { max: u32 },
+  #[error("图集中已存在 {name}")]
+  Duplicate { name: String },
+  #[error("空图片 {name}")]
+  Empty { name: String },
+  #[error("图片加载失败")]
+  Texture(#[from] texture::Error),
+}
+
+/// 子图在图集中的纹理坐标范围
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+  pub min: Vector2<f32>,
+  pub max: Vector2<f32>,
+}
+impl UvRect {
+  /// 将子图自身的纹理坐标([0, 1])映射为图集中的纹理坐标
+  pub fn map(&self, uv: Vector2<f32>) -> Vector2<f32> {
+    self.min + (self.max - self.min).component_mul(&uv)
+  }
+}
+
+/// 在CPU上将多张小图合并为一张图集
+pub struct AtlasBuilder {
+  images: Vec<(String, RgbaImage)>,
+  padding: u32,
+  max_size: u32,
+}
+impl Default for AtlasBuilder {
+  fn default() -> Self {
+    AtlasBuilder {
+      images: Vec::new(),
+      padding: 2,
+      max_size: 4096,
+    }
+  }
+}
+impl AtlasBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  /// 子图之间的间距，以子图边缘的像素填充，避免线性过滤与mipmap时相互渗色
+  pub fn padding(self, padding: u32) -> Self {
+    AtlasBuilder { padding, ..self }
+  }
+  /// 图集的最大边长
+  pub fn max_size(self, max_size: u32) -> Self {
+    AtlasBuilder { max_size, ..self }
+  }
+
+  pub fn add(&mut self, name: impl Into<String>, image: DynamicImage) -> Result<&mut Self, Error> {
+    let name = name.into();
+    if self.images.iter().any(|(exist, _)| *exist == name) {
+      return Err(Error::Duplicate { name });
+    }
+    if image.width() == 0 || image.height() == 0 {
+      return Err(Error::Empty { name });
+    }
+    self.images.push((name, image.to_rgba8()));
+    Ok(self)
+  }
+  /// 以资源名作为子图的名称
+  pub fn add_res(&mut self, res: &Resources, name: &str) -> Result<&mut Self, Error> {
+    let image = load_image(&res.resolve_path(name))?;
+    self.add(name, image)
+  }
+
+  /// 从能容纳所有子图面积的最小的2的幂开始，边长逐次加倍直到放得下
+  pub fn build(self) -> Result<Atlas, Error> {
+    let padded = |img: &RgbaImage| {
+      (
+        img.width() + self.padding * 2,
+        img.height() + self.padding * 2,
+      )
+    };
+    // 按高度从高到低逐行(shelf)摆放
+    let mut order: Vec<usize> = (0..self.images.len()).collect();
+    order.sort_by_key(|i| std::cmp::Reverse(self.images[*i].1.height()));
+    let area: u64 = self
+      .images
+      .iter()
+      .map(|(_, img)| {
+        let (w, h) = padded(img);
+        w as u64 * h as u64
+      })
+      .sum();
+    let mut size = ((area as f64).sqrt().ceil() as u32)
+      .next_power_of_two()
+      .max(1);
+    let positions = loop {
+      if size > self.max_size {
+        return Err(Error::TooLarge { max: self.max_size });
+      }
+      if let Some(positions) = pack(&order, size, |i| padded(&self.images[i].1)) {
+        break positions;
+      }
+      size *= 2;
+    };
+
+    let mut image = RgbaImage::new(size, size);
+    let mut rects = HashMap::new();
+    for (i, (x, y)) in positions {
+      let (name, src) = &self.images[i];
+      let (w, h) = src.dimensions();
+      let (pw, ph) = padded(src);
+      for dy in 0..ph {
+        for dx in 0..pw {
+          // 间距内取最近的边缘像素
+          let sx = dx.saturating_sub(self.padding).min(w - 1);
+          let sy = dy.saturating_sub(self.padding).min(h - 1);
+          image.put_pixel(x + dx, y + dy, *src.get_pixel(sx, sy));
+        }
+      }
+      let (x, y) = (x + self.padding, y + self.padding);
+      rects.insert(
+        name.clone(),
+        UvRect {
+          min: Vector2::new(x as f32, y as f32) / size as f32,
+          max: Vector2::new((x + w) as f32, (y + h) as f32) / size as f32,
+        },
+      );
+    }
+    Ok(Atlas { image, rects })
+  }
+}
+
+/// 在边长为size的正方形中逐行摆放，放不下时返回None
+fn pack(
+  order: &[usize],
+  size: u32,
+  dimensions: impl Fn(usize) -> (u32, u32),
+) -> Option<Vec<(usize, (u32, u32))>> {
+  let (mut x, mut y, mut shelf) = (0, 0, 0);
+  let mut positions = Vec::with_capacity(order.len());
+  for &i in order {
+    let (w, h) = dimensions(i);
+    if x + w > size {
+      x = 0;
+      y += shelf;
+      shelf = 0;
+    }
+    if w > size || y + h > size {
+      return None;
+    }
+    positions.push((i, (x, y)));
+    x += w;
+    shelf = shelf.max(h);
+  }
+  Some(positions)
+}
+
+pub struct Atlas {
+  image: RgbaImage,
+  rects: HashMap<String, UvRect>,
+}
+impl Atlas {
+  pub fn image(&self) -> &RgbaImage {
+    &self.image
+  }
+  pub fn rect(&self, name: &str) -> Option<UvRect> {
+    self.rects.get(name).copied()
+  }
+  pub fn rects(&self) -> &HashMap<String, UvRect> {
+    &self.rects
+  }
+  pub fn to_texture(&self, sampler: &SamplerDesc) -> Result<Texture, texture::Error> {
+    Texture::from_image_with(DynamicImage::ImageRgba8(self.image.clone()), sampler)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use image::Rgba;
+
+  use super::*;
+
+  const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+  const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+  fn solid(width: u32, height: u32, color: Rgba<u8>) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, color))
+  }
+
+  #[test]
+  fn pack_into_shelves() {
+    let dimensions = [(4, 4), (4, 2), (4, 3)];
+    let positions = pack(&[0, 1, 2], 8, |i| dimensions[i]).unwrap();
+    // 第三张换行到第一行最高的子图之下
+    assert_eq!(positions, vec![(0, (0, 0)), (1, (4, 0)), (2, (0, 4))]);
+  }
+
+  #[test]
+  fn pack_overflow() {
+    let dimensions = [(4, 4), (4, 4), (4, 4)];
+    assert_eq!(pack(&[0, 1, 2], 4, |i| dimensions[i]), None);
+    assert_eq!(pack(&[0], 2, |i| dimensions[i]), None);
+  }
+
+  #[test]
+  fn build_with_padding() {
+    let mut builder = AtlasBuilder::new().padding(1);
+    builder.add("red", solid(4, 4, RED)).unwrap();
+    builder.add("blue", solid(2, 2, BLUE)).unwrap();
+    let atlas = builder.build().unwrap();
+    // 加上间距后8x8放不下，边长加倍为16
+    assert_eq!(atlas.image().dimensions(), (16, 16));
+
+    let red = atlas.rect("red").unwrap();
+    assert_eq!(red.min, Vector2::new(1.0, 1.0) / 16.0);
+    assert_eq!(red.max, Vector2::new(5.0, 5.0) / 16.0);
+    let blue = atlas.rect("blue").unwrap();
+    assert_eq!(blue.min, Vector2::new(7.0, 1.0) / 16.0);
+    assert_eq!(blue.max, Vector2::new(9.0, 3.0) / 16.0);
+    assert_eq!(
+      blue.map(Vector2::new(0.5, 0.5)),
+      Vector2::new(8.0, 2.0) / 16.0
+    );
+  }
+
+  #[test]
+  fn padding_bleeds_edge_pixels() {
+    let mut builder = AtlasBuilder::new().padding(1);
+    builder.add("red", solid(4, 4, RED)).unwrap();
+    builder.add("blue", solid(2, 2, BLUE)).unwrap();
+    let atlas = builder.build().unwrap();
+    let image = atlas.image();
+    // 间距内为相邻子图的边缘像素，间距之外保持透明
+    for (x, y) in [(0, 0), (5, 0), (0, 5), (5, 5)] {
+      assert_eq!(*image.get_pixel(x, y), RED);
+    }
+    for (x, y) in [(6, 0), (9, 0), (6, 3), (9, 3)] {
+      assert_eq!(*image.get_pixel(x, y), BLUE);
+    }
+    assert_eq!(*image.get_pixel(10, 0), Rgba([0, 0, 0, 0]));
+    assert_eq!(*image.get_pixel(0, 6), Rgba([0, 0, 0, 0]));
+  }
+
+  #[test]
+  fn too_large() {
+    let mut builder = AtlasBuilder::new().padding(1).max_size(4);
+    builder.add("red", solid(4, 4, RED)).unwrap();
+    assert!(matches!(builder.build(), Err(Error::TooLarge { max: 4 })));
+  }
+
+  #[test]
+  fn reject_duplicate_and_empty() {
+    let mut builder = AtlasBuilder::new();
+    builder.add("red", solid(1, 1, RED)).unwrap();
+    assert!(matches!(
+      builder.add("red", solid(1, 1, RED)),
+      Err(Error::Duplicate { .. })
+    ));
+    assert!(matches!(
+      builder.add("empty", solid(0, 1, RED)),
+      Err(Error::Empty { .. })
+    ));
+  }
+}
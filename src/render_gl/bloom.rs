@@ -0,0 +1,186 @@
+use glow::HasContext;
+
+use crate::render_gl::buffer::VertexArray;
+use crate::render_gl::frame_buffer::{ColorFormat, Depth, FrameBuffer, FrameBufferDesc};
+use crate::render_gl::post::Error;
+use crate::render_gl::sampler::{Sampler, SamplerDesc};
+use crate::render_gl::Program;
+use crate::resources::Resources;
+use crate::GL;
+
+// 降采样的最大级数
+const MAX_LEVELS: usize = 6;
+// 最小一级的短边不小于该值
+const MIN_SIZE: i32 = 8;
+
+/// 泛光：提取高亮部分，逐级降采样再升采样模糊，最后叠加回原画面
+pub struct Bloom {
+  pub enabled: bool,
+  // 亮度超过阈值的部分才会泛光，阈值的一半以下完全不参与
+  // 默认值为2，不超过1的LDR画面不受影响
+  pub threshold: f32,
+  pub intensity: f32,
+  // 升采样时的采样间隔，越大光晕越宽
+  pub radius: f32,
+  prefilter: Program,
+  downsample: Program,
+  upsample: Program,
+  composite: Program,
+  // 第0级为一半分辨率，之后每级再减半
+  levels: Vec<FrameBuffer>,
+  output: FrameBuffer,
+  vao: VertexArray,
+  // 各级之间以边缘截取、线性过滤采样，画面边缘不会采样到另一侧
+  sampler: Sampler,
+}
+impl Bloom {
+  pub fn new(res: &Resources, width: i32, height: i32) -> Result<Bloom, Error> {
+    let load = |name: &str| {
+      Program::from_stages(
+        res,
+        &format!("shaders/post/bloom/{}", name),
+        vec![
+          "shaders/post/fullscreen.vert".to_string(),
+          format!("shaders/post/bloom/{}.frag", name),
+        ],
+      )
+    };
+    let prefilter = load("prefilter")?;
+    let downsample = load("downsample")?;
+    let upsample = load("upsample")?;
+    let composite = load("composite")?;
+    for program in [&prefilter, &downsample, &upsample, &composite] {
+      program.upload_texture_slot("frame", 0)?;
+    }
+    composite.upload_texture_slot("bloom", 1)?;
+    let (levels, output) = Self::create_targets(width, height)?;
+    Ok(Bloom {
+      enabled: true,
+      threshold: 2.0,
+      intensity: 0.3,
+      radius: 1.0,
+      prefilter,
+      downsample,
+      upsample,
+      composite,
+      levels,
+      output,
+      vao: VertexArray::new(),
+      sampler: Sampler::new(SamplerDesc::screen()),
+    })
+  }
+  fn create_targets(width: i32, height: i32) -> Result<(Vec<FrameBuffer>, FrameBuffer), Error> {
+    let desc = |width: i32, height: i32| {
+      FrameBufferDesc::new(width, height)
+        .color(ColorFormat::Rgba16F)
+        .depth(Depth::None)
+    };
+    let mut levels = Vec::with_capacity(MAX_LEVELS);
+    let (mut w, mut h) = (width / 2, height / 2);
+    while levels.len() < MAX_LEVELS && w.min(h) >= MIN_SIZE {
+      levels.push(desc(w, h).build()?);
+      w /= 2;
+      h /= 2;
+    }
+    Ok((levels, desc(width, height).build()?))
+  }
+  pub fn resize(&mut self, width: i32, height: i32) -> Result<(), Error> {
+    let (levels, output) = Self::create_targets(width, height)?;
+    self.levels = levels;
+    self.output = output;
+    Ok(())
+  }
+
+  /// 以input为输入，关闭或窗口过小时返回None
+  pub fn apply(&self, input: glow::Texture) -> Result<Option<&FrameBuffer>, Error> {
+    if !self.enabled || self.levels.is_empty() {
+      return Ok(None);
+    }
+    let mut viewport = [0; 4];
+    // 叠加时会修改混合状态，结束后恢复
+    let (blend, blend_src, blend_dst) = unsafe {
+      (
+        GL.is_enabled(glow::BLEND),
+        GL.get_parameter_i32(glow::BLEND_SRC_RGB) as u32,
+        GL.get_parameter_i32(glow::BLEND_DST_RGB) as u32,
+      )
+    };
+    unsafe {
+      GL.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+      GL.disable(glow::BLEND);
+      GL.active_texture(glow::TEXTURE0);
+    }
+    self.vao.bind();
+    // 叠加时第1个纹理单元为泛光结果
+    self.sampler.bind(0);
+    self.sampler.bind(1);
+    let result = self.draw_passes(input);
+    Sampler::unbind(0);
+    Sampler::unbind(1);
+    self.output.detach();
+    self.vao.unbind();
+    let [x, y, w, h] = viewport;
+    unsafe {
+      GL.viewport(x, y, w, h);
+      if blend {
+        GL.enable(glow::BLEND);
+      }
+      GL.blend_func(blend_src, blend_dst);
+    }
+    result.map(|()| Some(&self.output))
+  }
+  fn draw_passes(&self, input: glow::Texture) -> Result<(), Error> {
+    self.prefilter.set_used();
+    self.prefilter.upload_float("threshold", self.threshold)?;
+    // 过渡宽度取阈值的一半
+    self.prefilter.upload_float("knee", self.threshold * 0.5)?;
+    draw(input, &self.levels[0]);
+    self.downsample.set_used();
+    for pair in self.levels.windows(2) {
+      draw(pair[0].texture(), &pair[1]);
+    }
+
+    // 由最小一级开始，逐级升采样并叠加到上一级
+    self.upsample.set_used();
+    self.upsample.upload_float("radius", self.radius)?;
+    unsafe {
+      GL.enable(glow::BLEND);
+      GL.blend_func(glow::ONE, glow::ONE);
+    }
+    for pair in self.levels.windows(2).rev() {
+      draw(pair[1].texture(), &pair[0]);
+    }
+    unsafe {
+      GL.disable(glow::BLEND);
+    }
+
+    self.composite.set_used();
+    self.composite.upload_float("intensity", self.intensity)?;
+    unsafe {
+      GL.active_texture(glow::TEXTURE1);
+      GL.bind_texture(glow::TEXTURE_2D, Some(self.levels[0].texture()));
+      GL.active_texture(glow::TEXTURE0);
+    }
+    draw(input, &self.output);
+    Ok(())
+  }
+
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    ui.checkbox(&mut self.enabled, "泛光");
+    if self.enabled {
+      ui.add(egui::Slider::new(&mut self.threshold, 0.0..=5.0).text("阈值"));
+      ui.add(egui::Slider::new(&mut self.intensity, 0.0..=2.0).text("强度"));
+      ui.add(egui::Slider::new(&mut self.radius, 0.5..=4.0).text("半径"));
+    }
+  }
+}
+
+/// 以当前使用的着色程序将input绘制到整个target上
+fn draw(input: glow::Texture, target: &FrameBuffer) {
+  target.bind();
+  unsafe {
+    GL.viewport(0, 0, target.width, target.height);
+    GL.bind_texture(glow::TEXTURE_2D, Some(input));
+    GL.draw_arrays(glow::TRIANGLES, 0, 3);
+  }
+}
@@ -1,9 +1,20 @@
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicU32, Ordering};
 
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
 use glow::HasContext;
+use once_cell::sync::{Lazy, OnceCell};
+use thiserror::Error;
 
 use crate::GL;
 
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("uniform块 {block} 超出binding点上限 {max}")]
+  TooManyUniformBlocks { block: String, max: u32 },
+}
+
 pub trait BufferType {
   const BUFFER_TYPE: u32;
 }
@@ -50,6 +61,34 @@ where
       GL.buffer_data_u8_slice(B::BUFFER_TYPE, data, glow::STATIC_DRAW);
     }
   }
+
+  pub fn dynamic_draw_data<T>(&self, data: &[T])
+  where
+    T: Debug,
+  {
+    unsafe {
+      let data = another::any_as_u8_slice(data);
+      GL.buffer_data_u8_slice(B::BUFFER_TYPE, data, glow::DYNAMIC_DRAW);
+    }
+  }
+
+  /// 分配size字节的未初始化存储空间
+  pub fn allocate(&self, size: usize, usage: u32) {
+    unsafe {
+      GL.buffer_data_size(B::BUFFER_TYPE, size as i32, usage);
+    }
+  }
+
+  /// 从offset字节处开始更新部分数据，需先bind
+  pub fn sub_data<T>(&self, offset: usize, data: &[T])
+  where
+    T: Debug,
+  {
+    unsafe {
+      let data = another::any_as_u8_slice(data);
+      GL.buffer_sub_data_u8_slice(B::BUFFER_TYPE, offset as i32, data);
+    }
+  }
 }
 impl<B> Drop for Buffer<B>
 where
@@ -80,6 +119,78 @@ impl BufferType for BufferTypeElementArray {
   const BUFFER_TYPE: u32 = glow::ELEMENT_ARRAY_BUFFER;
 }
 
+//
+//********Uniform Buffer Object
+//
+pub type UniformBuffer = Buffer<BufferTypeUniform>;
+pub struct BufferTypeUniform;
+impl BufferType for BufferTypeUniform {
+  const BUFFER_TYPE: u32 = glow::UNIFORM_BUFFER;
+}
+impl UniformBuffer {
+  /// 将整个缓冲绑定到binding点
+  pub fn bind_base(&self, binding: u32) {
+    unsafe {
+      GL.bind_buffer_base(glow::UNIFORM_BUFFER, binding, Some(self.inner));
+    }
+  }
+  /// 将缓冲的[offset, offset+size)字节绑定到binding点
+  pub fn bind_range(&self, binding: u32, offset: usize, size: usize) {
+    unsafe {
+      GL.bind_buffer_range(
+        glow::UNIFORM_BUFFER,
+        binding,
+        Some(self.inner),
+        offset as i32,
+        size as i32,
+      );
+    }
+  }
+}
+
+//
+//********Shader Storage Buffer Object
+//
+pub type ShaderStorageBuffer = Buffer<BufferTypeShaderStorage>;
+pub struct BufferTypeShaderStorage;
+impl BufferType for BufferTypeShaderStorage {
+  const BUFFER_TYPE: u32 = glow::SHADER_STORAGE_BUFFER;
+}
+impl ShaderStorageBuffer {
+  /// 绑定到着色器中 layout(std430, binding = N) 的存储块
+  pub fn bind_base(&self, binding: u32) {
+    unsafe {
+      GL.bind_buffer_base(glow::SHADER_STORAGE_BUFFER, binding, Some(self.inner));
+    }
+  }
+}
+
+// uniform块名 -> binding点，同名的块在所有着色程序中共享同一binding点
+static UNIFORM_BINDINGS: Lazy<DashMap<String, u32>> = Lazy::new(DashMap::new);
+static NEXT_UNIFORM_BINDING: AtomicU32 = AtomicU32::new(0);
+static MAX_UNIFORM_BINDINGS: OnceCell<u32> = OnceCell::new();
+
+/// 获取uniform块对应的binding点，首次查询时分配
+/// 不同块名的数量超出 MAX_UNIFORM_BUFFER_BINDINGS 时返回错误
+pub fn uniform_binding(block: &str) -> Result<u32, Error> {
+  match UNIFORM_BINDINGS.entry(block.to_string()) {
+    Entry::Occupied(entry) => Ok(*entry.get()),
+    Entry::Vacant(entry) => {
+      let max = *MAX_UNIFORM_BINDINGS
+        .get_or_init(|| unsafe { GL.get_parameter_i32(glow::MAX_UNIFORM_BUFFER_BINDINGS) } as u32);
+      let binding = NEXT_UNIFORM_BINDING
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |next| {
+          (next < max).then(|| next + 1)
+        })
+        .map_err(|_| Error::TooManyUniformBlocks {
+          block: block.to_string(),
+          max,
+        })?;
+      Ok(*entry.insert(binding))
+    }
+  }
+}
+
 //
 //********Vertex Array Object
 //
@@ -0,0 +1,563 @@
+use ddsfile::{D3DFormat, Dds, DxgiFormat, FourCC};
+use glow::HasContext;
+
+use crate::GL;
+
+const KTX2_MAGIC: [u8; 12] = [
+  0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/// 4x4像素一块的BCn压缩格式
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Block {
+  Bc1,
+  // 带1位透明度的BC1
+  Bc1a,
+  Bc2,
+  Bc3,
+  Bc4,
+  Bc4Signed,
+  Bc5,
+  Bc5Signed,
+  Bc6h,
+  Bc6hSigned,
+  Bc7,
+}
+impl Block {
+  fn block_size(self) -> usize {
+    match self {
+      Block::Bc1 | Block::Bc1a | Block::Bc4 | Block::Bc4Signed => 8,
+      _ => 16,
+    }
+  }
+  fn gl_format(self, srgb: bool) -> u32 {
+    match (self, srgb) {
+      (Block::Bc1, false) => glow::COMPRESSED_RGB_S3TC_DXT1_EXT,
+      (Block::Bc1, true) => glow::COMPRESSED_SRGB_S3TC_DXT1_EXT,
+      (Block::Bc1a, false) => glow::COMPRESSED_RGBA_S3TC_DXT1_EXT,
+      (Block::Bc1a, true) => glow::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+      (Block::Bc2, false) => glow::COMPRESSED_RGBA_S3TC_DXT3_EXT,
+      (Block::Bc2, true) => glow::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT,
+      (Block::Bc3, false) => glow::COMPRESSED_RGBA_S3TC_DXT5_EXT,
+      (Block::Bc3, true) => glow::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+      (Block::Bc4, _) => glow::COMPRESSED_RED_RGTC1,
+      (Block::Bc4Signed, _) => glow::COMPRESSED_SIGNED_RED_RGTC1,
+      (Block::Bc5, _) => glow::COMPRESSED_RG_RGTC2,
+      (Block::Bc5Signed, _) => glow::COMPRESSED_SIGNED_RG_RGTC2,
+      (Block::Bc6h, _) => glow::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+      (Block::Bc6hSigned, _) => glow::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+      (Block::Bc7, false) => glow::COMPRESSED_RGBA_BPTC_UNORM,
+      (Block::Bc7, true) => glow::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+    }
+  }
+  /// 当前上下文能否直接使用该格式
+  /// S3TC(BC1~3)一直是扩展，RGTC(BC4~5)与BPTC(BC6H~7)分别在3.0与4.2进入核心
+  fn supported(self) -> bool {
+    let extensions = GL.supported_extensions();
+    let version = GL.version();
+    let at_least =
+      |major, minor| !version.is_embedded && (version.major, version.minor) >= (major, minor);
+    match self {
+      Block::Bc1 | Block::Bc1a | Block::Bc2 | Block::Bc3 => {
+        extensions.contains("GL_EXT_texture_compression_s3tc")
+      }
+      Block::Bc4 | Block::Bc4Signed | Block::Bc5 | Block::Bc5Signed => {
+        at_least(3, 0) || extensions.contains("GL_ARB_texture_compression_rgtc")
+      }
+      Block::Bc6h | Block::Bc6hSigned | Block::Bc7 => {
+        at_least(4, 2) || extensions.contains("GL_ARB_texture_compression_bptc")
+      }
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Format {
+  Block(Block),
+  // 未压缩的8位RGBA与BGRA
+  Rgba8,
+  Bgra8,
+}
+impl Format {
+  /// 宽高为width x height的一层mip所占的字节数
+  fn level_size(self, width: u32, height: u32) -> usize {
+    match self {
+      Format::Block(block) => blocks(width) * blocks(height) * block.block_size(),
+      Format::Rgba8 | Format::Bgra8 => width as usize * height as usize * 4,
+    }
+  }
+}
+
+/// 长度为n的一行所需的块数，不足一块按一块计
+fn blocks(n: u32) -> usize {
+  (n as usize + 3) >> 2
+}
+
+/// 从KTX2或DDS文件中读出的二维纹理
+pub(super) struct Container {
+  pub format: Format,
+  pub srgb: bool,
+  pub width: u32,
+  pub height: u32,
+  // 预先生成的mip链，从第0层开始
+  pub levels: Vec<Vec<u8>>,
+}
+
+/// 按文件头判断是否为KTX2或DDS文件
+pub(super) fn is_container(bytes: &[u8]) -> bool {
+  bytes.starts_with(&KTX2_MAGIC) || bytes.starts_with(&DDS_MAGIC)
+}
+
+pub(super) fn parse(bytes: &[u8]) -> Result<Container, String> {
+  if bytes.starts_with(&KTX2_MAGIC) {
+    parse_ktx2(bytes)
+  } else {
+    parse_dds(bytes)
+  }
+}
+
+fn parse_ktx2(bytes: &[u8]) -> Result<Container, String> {
+  let reader = ktx2::Reader::new(bytes).map_err(|e| format!("KTX2解析失败 {:?}", e))?;
+  let header = reader.header();
+  if header.layer_count > 1 || header.face_count != 1 || header.pixel_depth > 1 {
+    return Err("仅支持二维纹理".to_string());
+  }
+  if let Some(scheme) = header.supercompression_scheme {
+    return Err(format!("不支持的超压缩方式 {:?}", scheme));
+  }
+  use ktx2::Format as K;
+  let (format, srgb) = match header.format {
+    Some(K::BC1_RGB_UNORM_BLOCK) => (Format::Block(Block::Bc1), false),
+    Some(K::BC1_RGB_SRGB_BLOCK) => (Format::Block(Block::Bc1), true),
+    Some(K::BC1_RGBA_UNORM_BLOCK) => (Format::Block(Block::Bc1a), false),
+    Some(K::BC1_RGBA_SRGB_BLOCK) => (Format::Block(Block::Bc1a), true),
+    Some(K::BC2_UNORM_BLOCK) => (Format::Block(Block::Bc2), false),
+    Some(K::BC2_SRGB_BLOCK) => (Format::Block(Block::Bc2), true),
+    Some(K::BC3_UNORM_BLOCK) => (Format::Block(Block::Bc3), false),
+    Some(K::BC3_SRGB_BLOCK) => (Format::Block(Block::Bc3), true),
+    Some(K::BC4_UNORM_BLOCK) => (Format::Block(Block::Bc4), false),
+    Some(K::BC4_SNORM_BLOCK) => (Format::Block(Block::Bc4Signed), false),
+    Some(K::BC5_UNORM_BLOCK) => (Format::Block(Block::Bc5), false),
+    Some(K::BC5_SNORM_BLOCK) => (Format::Block(Block::Bc5Signed), false),
+    Some(K::BC6H_UFLOAT_BLOCK) => (Format::Block(Block::Bc6h), false),
+    Some(K::BC6H_SFLOAT_BLOCK) => (Format::Block(Block::Bc6hSigned), false),
+    Some(K::BC7_UNORM_BLOCK) => (Format::Block(Block::Bc7), false),
+    Some(K::BC7_SRGB_BLOCK) => (Format::Block(Block::Bc7), true),
+    Some(K::R8G8B8A8_UNORM) => (Format::Rgba8, false),
+    Some(K::R8G8B8A8_SRGB) => (Format::Rgba8, true),
+    Some(K::B8G8R8A8_UNORM) => (Format::Bgra8, false),
+    Some(K::B8G8R8A8_SRGB) => (Format::Bgra8, true),
+    // 未指定格式时为Basis Universal，需要转码器
+    other => return Err(format!("不支持的KTX2格式 {:?}", other)),
+  };
+  let levels = reader.levels().map(|level| level.data.to_vec()).collect();
+  check_levels(Container {
+    format,
+    srgb,
+    width: header.pixel_width,
+    height: header.pixel_height.max(1),
+    levels,
+  })
+}
+
+fn parse_dds(bytes: &[u8]) -> Result<Container, String> {
+  let dds = Dds::read(bytes).map_err(|e| format!("DDS解析失败 {}", e))?;
+  if dds.get_num_array_layers() != 1 || dds.get_depth() > 1 {
+    return Err("仅支持二维纹理".to_string());
+  }
+  let (format, srgb) = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+    (Some(dxgi), _) => match dxgi {
+      DxgiFormat::BC1_UNorm => (Format::Block(Block::Bc1a), false),
+      DxgiFormat::BC1_UNorm_sRGB => (Format::Block(Block::Bc1a), true),
+      DxgiFormat::BC2_UNorm => (Format::Block(Block::Bc2), false),
+      DxgiFormat::BC2_UNorm_sRGB => (Format::Block(Block::Bc2), true),
+      DxgiFormat::BC3_UNorm => (Format::Block(Block::Bc3), false),
+      DxgiFormat::BC3_UNorm_sRGB => (Format::Block(Block::Bc3), true),
+      DxgiFormat::BC4_UNorm => (Format::Block(Block::Bc4), false),
+      DxgiFormat::BC4_SNorm => (Format::Block(Block::Bc4Signed), false),
+      DxgiFormat::BC5_UNorm => (Format::Block(Block::Bc5), false),
+      DxgiFormat::BC5_SNorm => (Format::Block(Block::Bc5Signed), false),
+      DxgiFormat::BC6H_UF16 => (Format::Block(Block::Bc6h), false),
+      DxgiFormat::BC6H_SF16 => (Format::Block(Block::Bc6hSigned), false),
+      DxgiFormat::BC7_UNorm => (Format::Block(Block::Bc7), false),
+      DxgiFormat::BC7_UNorm_sRGB => (Format::Block(Block::Bc7), true),
+      DxgiFormat::R8G8B8A8_UNorm => (Format::Rgba8, false),
+      DxgiFormat::R8G8B8A8_UNorm_sRGB => (Format::Rgba8, true),
+      DxgiFormat::B8G8R8A8_UNorm => (Format::Bgra8, false),
+      DxgiFormat::B8G8R8A8_UNorm_sRGB => (Format::Bgra8, true),
+      other => return Err(format!("不支持的DXGI格式 {:?}", other)),
+    },
+    // DXT1中可能含有1位透明度
+    (_, Some(D3DFormat::DXT1)) => (Format::Block(Block::Bc1a), false),
+    (_, Some(D3DFormat::DXT3)) => (Format::Block(Block::Bc2), false),
+    (_, Some(D3DFormat::DXT5)) => (Format::Block(Block::Bc3), false),
+    (_, Some(D3DFormat::A8B8G8R8)) => (Format::Rgba8, false),
+    (_, Some(D3DFormat::A8R8G8B8)) => (Format::Bgra8, false),
+    // 旧式文件头中的BC4/BC5
+    _ => match dds.header.spf.fourcc.as_ref().map(|fourcc| fourcc.0) {
+      Some(FourCC::ATI1) | Some(FourCC::BC4_UNORM) => (Format::Block(Block::Bc4), false),
+      Some(FourCC::ATI2) => (Format::Block(Block::Bc5), false),
+      _ => return Err("不支持的DDS格式".to_string()),
+    },
+  };
+  let (width, height) = (dds.get_width(), dds.get_height());
+  // DDS中各层mip紧密排列
+  let mut data = dds
+    .get_data(0)
+    .map_err(|e| format!("DDS数据读取失败 {}", e))?;
+  let mut levels = Vec::new();
+  for level in 0..dds.get_num_mipmap_levels().max(1) {
+    let size = format.level_size((width >> level).max(1), (height >> level).max(1));
+    if data.len() < size {
+      break;
+    }
+    let (head, tail) = data.split_at(size);
+    levels.push(head.to_vec());
+    data = tail;
+  }
+  check_levels(Container {
+    format,
+    srgb,
+    width,
+    height,
+    levels,
+  })
+}
+
+/// 检查每层mip的数据量是否与尺寸相符
+fn check_levels(container: Container) -> Result<Container, String> {
+  if container.levels.is_empty() {
+    return Err("纹理不包含任何数据".to_string());
+  }
+  for (level, data) in container.levels.iter().enumerate() {
+    let (width, height) = level_dimensions(&container, level);
+    let expected = container.format.level_size(width, height);
+    if data.len() < expected {
+      return Err(format!(
+        "第{}层mip数据不足 {} < {}",
+        level,
+        data.len(),
+        expected
+      ));
+    }
+  }
+  Ok(container)
+}
+
+fn level_dimensions(container: &Container, level: usize) -> (u32, u32) {
+  (
+    (container.width >> level).max(1),
+    (container.height >> level).max(1),
+  )
+}
+
+/// 将所有mip层级上传到当前绑定的TEXTURE_2D
+/// 驱动不支持的块压缩格式在CPU上解压为RGBA8后上传，返回是否以压缩格式上传
+pub(super) unsafe fn upload(container: &Container) -> Result<bool, String> {
+  let uploaded_compressed = match container.format {
+    Format::Block(block) if block.supported() => {
+      let internal = block.gl_format(container.srgb);
+      for (level, data) in container.levels.iter().enumerate() {
+        let (width, height) = level_dimensions(container, level);
+        let size = container.format.level_size(width, height);
+        GL.compressed_tex_image_2d(
+          glow::TEXTURE_2D,
+          level as i32,
+          internal as i32,
+          width as i32,
+          height as i32,
+          0,
+          size as i32,
+          &data[..size],
+        );
+      }
+      true
+    }
+    format => {
+      let (pixel_format, decompress) = match format {
+        Format::Block(block) => (glow::RGBA, Some(block)),
+        Format::Rgba8 => (glow::RGBA, None),
+        Format::Bgra8 => (glow::BGRA, None),
+      };
+      let internal = if container.srgb {
+        glow::SRGB8_ALPHA8
+      } else {
+        glow::RGBA8
+      };
+      GL.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+      for (level, data) in container.levels.iter().enumerate() {
+        let (width, height) = level_dimensions(container, level);
+        let pixels = match decompress {
+          Some(block) => decompress_level(block, width, height, data)?,
+          None => data[..container.format.level_size(width, height)].to_vec(),
+        };
+        GL.tex_image_2d(
+          glow::TEXTURE_2D,
+          level as i32,
+          internal as i32,
+          width as i32,
+          height as i32,
+          0,
+          pixel_format,
+          glow::UNSIGNED_BYTE,
+          Some(&pixels),
+        );
+      }
+      GL.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+      false
+    }
+  };
+  GL.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_BASE_LEVEL, 0);
+  GL.tex_parameter_i32(
+    glow::TEXTURE_2D,
+    glow::TEXTURE_MAX_LEVEL,
+    container.levels.len() as i32 - 1,
+  );
+  Ok(uploaded_compressed)
+}
+
+/// 在CPU上将一层BC1~BC5数据解压为RGBA8
+fn decompress_level(block: Block, width: u32, height: u32, data: &[u8]) -> Result<Vec<u8>, String> {
+  let (width, height) = (width as usize, height as usize);
+  let mut pixels = vec![0u8; width * height * 4];
+  let blocks_x = blocks(width as u32);
+  let size = block.block_size();
+  for (i, chunk) in data.chunks_exact(size).enumerate() {
+    let (bx, by) = (i % blocks_x * 4, i / blocks_x * 4);
+    if by >= height {
+      break;
+    }
+    let texels = decode_block(block, chunk)?;
+    for (j, texel) in texels.iter().enumerate() {
+      let (x, y) = (bx + j % 4, by + j / 4);
+      if x < width && y < height {
+        let offset = (y * width + x) * 4;
+        pixels[offset..offset + 4].copy_from_slice(texel);
+      }
+    }
+  }
+  Ok(pixels)
+}
+
+/// 解码一个4x4的块，结果按行排列
+fn decode_block(block: Block, data: &[u8]) -> Result<[[u8; 4]; 16], String> {
+  let mut texels = [[0, 0, 0, 255]; 16];
+  match block {
+    Block::Bc1 => decode_color(&data[0..8], Some(255), &mut texels),
+    Block::Bc1a => decode_color(&data[0..8], Some(0), &mut texels),
+    Block::Bc2 => {
+      decode_color(&data[8..16], None, &mut texels);
+      // 每像素4位的显式透明度
+      for (i, texel) in texels.iter_mut().enumerate() {
+        let nibble = (data[i / 2] >> (i % 2 * 4)) & 0xF;
+        texel[3] = nibble * 17;
+      }
+    }
+    Block::Bc3 => {
+      decode_color(&data[8..16], None, &mut texels);
+      for (texel, alpha) in texels.iter_mut().zip(decode_channel(&data[0..8])) {
+        texel[3] = alpha;
+      }
+    }
+    // 与GL中RGTC的语义一致，只有R(G)通道
+    Block::Bc4 => {
+      for (texel, red) in texels.iter_mut().zip(decode_channel(&data[0..8])) {
+        *texel = [red, 0, 0, 255];
+      }
+    }
+    Block::Bc5 => {
+      let red = decode_channel(&data[0..8]);
+      let green = decode_channel(&data[8..16]);
+      for (i, texel) in texels.iter_mut().enumerate() {
+        *texel = [red[i], green[i], 0, 255];
+      }
+    }
+    other => return Err(format!("驱动不支持{:?}，且无法在CPU上解压", other)),
+  }
+  Ok(texels)
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+  let r = ((color >> 11) & 0x1F) as u8;
+  let g = ((color >> 5) & 0x3F) as u8;
+  let b = (color & 0x1F) as u8;
+  [
+    (r << 3) | (r >> 2),
+    (g << 2) | (g >> 4),
+    (b << 3) | (b >> 2),
+    255,
+  ]
+}
+
+/// 颜色块，bc1_alpha为BC1三色模式中索引3的透明度
+/// BC2/BC3中的颜色块(bc1_alpha为None)总是使用四色模式
+fn decode_color(data: &[u8], bc1_alpha: Option<u8>, texels: &mut [[u8; 4]; 16]) {
+  let c0 = u16::from_le_bytes([data[0], data[1]]);
+  let c1 = u16::from_le_bytes([data[2], data[3]]);
+  let (p0, p1) = (rgb565(c0), rgb565(c1));
+  let mix = |a: u8, b: u8, wa: u32, wb: u32| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+  let lerp = |wa, wb| {
+    [
+      mix(p0[0], p1[0], wa, wb),
+      mix(p0[1], p1[1], wa, wb),
+      mix(p0[2], p1[2], wa, wb),
+      255,
+    ]
+  };
+  let palette = match bc1_alpha {
+    Some(alpha) if c0 <= c1 => [p0, p1, lerp(1, 1), [0, 0, 0, alpha]],
+    _ => [p0, p1, lerp(2, 1), lerp(1, 2)],
+  };
+  let indices = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+  for (i, texel) in texels.iter_mut().enumerate() {
+    *texel = palette[(indices >> (i * 2) & 0b11) as usize];
+  }
+}
+
+/// BC4的单通道块，也用于BC3的透明度与BC5的两个通道
+fn decode_channel(data: &[u8]) -> [u8; 16] {
+  let (a0, a1) = (data[0] as u32, data[1] as u32);
+  // 索引0、1为端点，其后为插值；端点不降序时最后两个索引固定为0与255
+  let steps = if a0 > a1 { 7 } else { 5 };
+  let mut palette = [a0, a1, 0, 0, 0, 0, 0, 255];
+  for (i, value) in palette[2..steps + 1].iter_mut().enumerate() {
+    let i = i as u32 + 1;
+    *value = (a0 * (steps as u32 - i) + a1 * i) / steps as u32;
+  }
+  let mut bits = 0u64;
+  for (i, byte) in data[2..8].iter().enumerate() {
+    bits |= (*byte as u64) << (i * 8);
+  }
+  let mut values = [0u8; 16];
+  for (i, value) in values.iter_mut().enumerate() {
+    *value = palette[(bits >> (i * 3) & 0b111) as usize] as u8;
+  }
+  values
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RED: [u8; 4] = [255, 0, 0, 255];
+  const BLUE: [u8; 4] = [0, 0, 255, 255];
+  // 每行的索引依次为0、1、2、3
+  const ROW_INDICES: [u8; 4] = [0xE4; 4];
+  // 16个像素的3位索引依次为0~7、0~7
+  const CHANNEL_INDICES: [u8; 6] = [0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA];
+
+  fn color_block(c0: u16, c1: u16, indices: [u8; 4]) -> Vec<u8> {
+    let mut block = Vec::new();
+    block.extend_from_slice(&c0.to_le_bytes());
+    block.extend_from_slice(&c1.to_le_bytes());
+    block.extend_from_slice(&indices);
+    block
+  }
+
+  fn channel_block(a0: u8, a1: u8) -> Vec<u8> {
+    let mut block = vec![a0, a1];
+    block.extend_from_slice(&CHANNEL_INDICES);
+    block
+  }
+
+  fn rows(row: [[u8; 4]; 4]) -> [[u8; 4]; 16] {
+    let mut texels = [[0; 4]; 16];
+    for (i, texel) in texels.iter_mut().enumerate() {
+      *texel = row[i % 4];
+    }
+    texels
+  }
+
+  #[test]
+  fn bc1_four_color_mode() {
+    let block = color_block(0xF800, 0x001F, ROW_INDICES);
+    let texels = decode_block(Block::Bc1, &block).unwrap();
+    assert_eq!(
+      texels,
+      rows([RED, BLUE, [170, 0, 85, 255], [85, 0, 170, 255]])
+    );
+  }
+
+  #[test]
+  fn bc1_three_color_mode() {
+    // c0 <= c1 时索引2为中点，索引3为黑色，BC1a中为透明
+    let block = color_block(0x001F, 0xF800, ROW_INDICES);
+    let texels = decode_block(Block::Bc1, &block).unwrap();
+    assert_eq!(
+      texels,
+      rows([BLUE, RED, [127, 0, 127, 255], [0, 0, 0, 255]])
+    );
+    let texels = decode_block(Block::Bc1a, &block).unwrap();
+    assert_eq!(texels, rows([BLUE, RED, [127, 0, 127, 255], [0, 0, 0, 0]]));
+  }
+
+  #[test]
+  fn bc2_explicit_alpha() {
+    let mut block = vec![0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE];
+    // 端点相同时BC2仍使用四色模式，不会出现透明的黑色
+    block.extend(color_block(0xFFFF, 0xFFFF, ROW_INDICES));
+    let texels = decode_block(Block::Bc2, &block).unwrap();
+    for (i, texel) in texels.iter().enumerate() {
+      assert_eq!(*texel, [255, 255, 255, i as u8 * 17]);
+    }
+  }
+
+  #[test]
+  fn bc3_interpolated_alpha() {
+    let mut block = channel_block(255, 0);
+    block.extend(color_block(0xF800, 0xF800, [0; 4]));
+    let texels = decode_block(Block::Bc3, &block).unwrap();
+    let alpha = [255, 0, 218, 182, 145, 109, 72, 36];
+    for (i, texel) in texels.iter().enumerate() {
+      assert_eq!(*texel, [255, 0, 0, alpha[i % 8]]);
+    }
+  }
+
+  #[test]
+  fn channel_eight_value_mode() {
+    let values = decode_channel(&channel_block(255, 0));
+    let palette = [255, 0, 218, 182, 145, 109, 72, 36];
+    for (i, value) in values.iter().enumerate() {
+      assert_eq!(*value, palette[i % 8]);
+    }
+  }
+
+  #[test]
+  fn channel_six_value_mode() {
+    // a0 <= a1 时只插值4个，最后两个索引为0与255
+    let values = decode_channel(&channel_block(0, 100));
+    let palette = [0, 100, 20, 40, 60, 80, 0, 255];
+    for (i, value) in values.iter().enumerate() {
+      assert_eq!(*value, palette[i % 8]);
+    }
+  }
+
+  #[test]
+  fn bc4_and_bc5_channels() {
+    let texels = decode_block(Block::Bc4, &channel_block(0, 100)).unwrap();
+    assert_eq!(texels[1], [100, 0, 0, 255]);
+    assert_eq!(texels[7], [255, 0, 0, 255]);
+
+    let mut block = channel_block(255, 0);
+    block.extend(channel_block(0, 100));
+    let texels = decode_block(Block::Bc5, &block).unwrap();
+    assert_eq!(texels[0], [255, 0, 0, 255]);
+    assert_eq!(texels[2], [218, 20, 0, 255]);
+    assert_eq!(texels[15], [36, 255, 0, 255]);
+  }
+
+  #[test]
+  fn bptc_is_not_decompressed() {
+    assert!(decode_block(Block::Bc7, &[0; 16]).is_err());
+    assert!(decode_block(Block::Bc6h, &[0; 16]).is_err());
+  }
+
+  #[test]
+  fn decompress_partial_blocks() {
+    // 2x2的层级只取块的左上角
+    let block = color_block(0xF800, 0x001F, ROW_INDICES);
+    let pixels = decompress_level(Block::Bc1, 2, 2, &block).unwrap();
+    assert_eq!(pixels, [RED, BLUE, RED, BLUE].concat());
+  }
+}
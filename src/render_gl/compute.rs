@@ -0,0 +1,44 @@
+use std::ops::Deref;
+
+use glow::HasContext;
+
+use crate::render_gl::{Error, Program};
+use crate::resources::Resources;
+use crate::GL;
+
+/// 仅含计算着色器阶段的着色程序
+/// uniform的上传与普通着色程序相同
+pub struct ComputeProgram {
+  program: Program,
+}
+
+impl ComputeProgram {
+  /// name: 不含扩展名的资源名，加载 name.comp
+  pub fn from_res(res: &Resources, name: &str) -> Result<ComputeProgram, Error> {
+    let program = Program::from_stages(res, name, vec![format!("{}.comp", name)])?;
+    Ok(ComputeProgram { program })
+  }
+
+  /// 以 x*y*z 个工作组执行计算着色器
+  pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+    self.program.set_used();
+    unsafe {
+      GL.dispatch_compute(x, y, z);
+    }
+  }
+
+  /// 等待计算着色器的写入对后续的读取可见
+  /// barriers: 如 glow::SHADER_STORAGE_BARRIER_BIT | glow::VERTEX_ATTRIB_ARRAY_BARRIER_BIT
+  pub fn memory_barrier(barriers: u32) {
+    unsafe {
+      GL.memory_barrier(barriers);
+    }
+  }
+}
+
+impl Deref for ComputeProgram {
+  type Target = Program;
+  fn deref(&self) -> &Program {
+    &self.program
+  }
+}
@@ -0,0 +1,296 @@
+use std::path::{Path, PathBuf};
+
+use glow::HasContext;
+use image::{DynamicImage, GenericImageView, Rgb32FImage};
+
+use crate::render_gl::sampler::{SamplerDesc, Wrap};
+use crate::render_gl::texture::{load_image, upload_texture_data, Error, PixelFormat};
+use crate::{resources::Resources, GL};
+
+/// 各面的文件名，顺序与 TEXTURE_CUBE_MAP_POSITIVE_X + i 一致
+/// 即 +X, -X, +Y, -Y, +Z, -Z
+pub const FACE_NAMES: [&str; 6] = ["right", "left", "top", "bottom", "front", "back"];
+
+/// 单张图片中各面所在的格子(列, 行)，以及是否需要旋转180度
+type Layout = [(u32, u32, bool); 6];
+//     +Y
+//  -X +Z +X -Z
+//     -Y
+const HORIZONTAL_CROSS: Layout = [
+  (2, 1, false),
+  (0, 1, false),
+  (1, 0, false),
+  (1, 2, false),
+  (1, 1, false),
+  (3, 1, false),
+];
+//     +Y
+//  -X +Z +X
+//     -Y
+//     -Z (倒置)
+const VERTICAL_CROSS: Layout = [
+  (2, 1, false),
+  (0, 1, false),
+  (1, 0, false),
+  (1, 2, false),
+  (1, 1, false),
+  (1, 3, true),
+];
+const HORIZONTAL_STRIP: Layout = [
+  (0, 0, false),
+  (1, 0, false),
+  (2, 0, false),
+  (3, 0, false),
+  (4, 0, false),
+  (5, 0, false),
+];
+const VERTICAL_STRIP: Layout = [
+  (0, 0, false),
+  (0, 1, false),
+  (0, 2, false),
+  (0, 3, false),
+  (0, 4, false),
+  (0, 5, false),
+];
+
+pub struct Cubemap {
+  inner: glow::Texture,
+  size: u32,
+}
+impl Cubemap {
+  /// 立方体贴图默认的采样参数：边缘截取以避免接缝，不使用mipmap，按sRGB解码
+  pub fn default_sampler() -> SamplerDesc {
+    SamplerDesc::srgb().wrap(Wrap::ClampToEdge).mipmap(None)
+  }
+
+  /// 由六张已解码的正方形图片创建，顺序见`FACE_NAMES`
+  pub fn from_images(faces: [DynamicImage; 6], sampler: &SamplerDesc) -> Result<Cubemap, Error> {
+    let size = faces[0].width();
+    if let Some(face) = faces
+      .iter()
+      .find(|face| face.width() != size || face.height() != size)
+    {
+      return Err(Error::LoadError(format!(
+        "立方体贴图的各面须为相同大小的正方形 {}x{} != {}x{}",
+        face.width(),
+        face.height(),
+        size,
+        size
+      )));
+    }
+    // 各面的内部格式也须一致
+    let faces = if faces.iter().all(|face| face.color() == faces[0].color()) {
+      faces
+    } else {
+      faces.map(|face| DynamicImage::ImageRgba8(face.to_rgba8()))
+    };
+
+    let texture = unsafe { GL.create_texture().unwrap() };
+    unsafe {
+      GL.bind_texture(glow::TEXTURE_CUBE_MAP, Some(texture));
+      let mut face_format = None;
+      for (i, face) in faces.into_iter().enumerate() {
+        let (face, format) = PixelFormat::normalize(face, sampler.color_space);
+        upload_texture_data(
+          glow::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
+          size,
+          size,
+          &format,
+          face.as_bytes(),
+        );
+        face_format = Some(format);
+      }
+      if let Some(format) = face_format {
+        format.apply_swizzle(glow::TEXTURE_CUBE_MAP);
+      }
+      if sampler.mipmap.is_some() {
+        GL.generate_mipmap(glow::TEXTURE_CUBE_MAP);
+      }
+      sampler.apply_to_texture(glow::TEXTURE_CUBE_MAP);
+    }
+    Ok(Cubemap {
+      inner: texture,
+      size,
+    })
+  }
+
+  /// 由十字形或条带形排列的单张图片切分出六个面
+  /// 根据宽高比识别 4x3/3x4 的十字形与 6x1/1x6 的条带形
+  pub fn from_single_image(img: DynamicImage, sampler: &SamplerDesc) -> Result<Cubemap, Error> {
+    let (width, height) = img.dimensions();
+    let (layout, size) = if width * 3 == height * 4 {
+      (HORIZONTAL_CROSS, width / 4)
+    } else if width * 4 == height * 3 {
+      (VERTICAL_CROSS, width / 3)
+    } else if width == height * 6 {
+      (HORIZONTAL_STRIP, height)
+    } else if height == width * 6 {
+      (VERTICAL_STRIP, width)
+    } else {
+      return Err(Error::LoadError(format!(
+        "无法识别的立方体贴图布局 {}x{}",
+        width, height
+      )));
+    };
+    let faces = layout.map(|(col, row, flip)| {
+      let face = img.crop_imm(col * size, row * size, size, size);
+      if flip {
+        face.rotate180()
+      } else {
+        face
+      }
+    });
+    Self::from_images(faces, sampler)
+  }
+
+  /// 按`FACE_NAMES`的顺序给出六个面的资源名
+  pub fn from_faces(
+    res: &Resources,
+    names: &[&str; 6],
+    sampler: &SamplerDesc,
+  ) -> Result<Cubemap, Error> {
+    let mut faces = Vec::with_capacity(6);
+    for name in names {
+      faces.push(load_image(&res.resolve_path(name))?);
+    }
+    let faces: [DynamicImage; 6] = faces.try_into().unwrap();
+    Self::from_images(faces, sampler)
+  }
+
+  /// name为目录时，读取其中以`FACE_NAMES`命名的六张图片(扩展名任意)
+  /// 否则视为一张十字形或条带形的图片
+  pub fn from_res(res: &Resources, name: &str) -> Result<Cubemap, Error> {
+    Self::from_res_with(res, name, &Self::default_sampler())
+  }
+  pub fn from_res_with(
+    res: &Resources,
+    name: &str,
+    sampler: &SamplerDesc,
+  ) -> Result<Cubemap, Error> {
+    let path = res.resolve_path(name);
+    if !path.is_dir() {
+      return Self::from_single_image(load_image(&path)?, sampler);
+    }
+    let mut faces = Vec::with_capacity(6);
+    for face in FACE_NAMES {
+      faces.push(load_image(&find_face(&path, face)?)?);
+    }
+    let faces: [DynamicImage; 6] = faces.try_into().unwrap();
+    Self::from_images(faces, sampler)
+  }
+
+  /// 创建各面内容为空的浮点(RGB16F)立方体贴图，用作渲染目标
+  /// levels: 分配的mip层数
+  pub fn new_hdr(size: u32, levels: u32, sampler: &SamplerDesc) -> Cubemap {
+    let texture = unsafe { GL.create_texture().unwrap() };
+    unsafe {
+      GL.bind_texture(glow::TEXTURE_CUBE_MAP, Some(texture));
+      for level in 0..levels {
+        let level_size = (size >> level).max(1) as i32;
+        for face in 0..6 {
+          GL.tex_image_2d(
+            glow::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+            level as i32,
+            glow::RGB16F as i32,
+            level_size,
+            level_size,
+            0,
+            glow::RGB,
+            glow::FLOAT,
+            None,
+          );
+        }
+      }
+      GL.tex_parameter_i32(
+        glow::TEXTURE_CUBE_MAP,
+        glow::TEXTURE_MAX_LEVEL,
+        levels as i32 - 1,
+      );
+      sampler.apply_to_texture(glow::TEXTURE_CUBE_MAP);
+    }
+    Cubemap {
+      inner: texture,
+      size,
+    }
+  }
+  /// 由第0层生成其余各层mip
+  pub fn generate_mipmap(&self) {
+    self.bind();
+    unsafe {
+      GL.generate_mipmap(glow::TEXTURE_CUBE_MAP);
+    }
+  }
+  /// 读取某一面某一层mip的浮点数据，行序与上传时一致
+  pub fn read_face(&self, face: u32, level: u32) -> Rgb32FImage {
+    let level_size = (self.size >> level).max(1);
+    let mut pixels = vec![0f32; (level_size * level_size * 3) as usize];
+    self.bind();
+    unsafe {
+      GL.pixel_store_i32(glow::PACK_ALIGNMENT, 4);
+      GL.get_tex_image(
+        glow::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+        level as i32,
+        glow::RGB,
+        glow::FLOAT,
+        glow::PixelPackData::Slice(another::any_as_u8_slice_mut(&mut pixels)),
+      );
+    }
+    Rgb32FImage::from_raw(level_size, level_size, pixels).unwrap()
+  }
+  /// 以浮点数据覆盖某一面某一层mip，尺寸需与该层一致
+  pub fn write_face(&self, face: u32, level: u32, image: &Rgb32FImage) {
+    self.bind();
+    unsafe {
+      GL.tex_sub_image_2d(
+        glow::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+        level as i32,
+        0,
+        0,
+        image.width() as i32,
+        image.height() as i32,
+        glow::RGB,
+        glow::FLOAT,
+        glow::PixelUnpackData::Slice(another::any_as_u8_slice(image.as_raw())),
+      );
+    }
+  }
+
+  pub(super) fn raw(&self) -> glow::Texture {
+    self.inner
+  }
+  /// 每个面的边长
+  pub fn size(&self) -> u32 {
+    self.size
+  }
+  pub fn bind(&self) {
+    unsafe {
+      GL.bind_texture(glow::TEXTURE_CUBE_MAP, Some(self.inner));
+    }
+  }
+  pub fn detach(&self) {
+    unsafe {
+      GL.bind_texture(glow::TEXTURE_CUBE_MAP, None);
+    }
+  }
+}
+impl Drop for Cubemap {
+  fn drop(&mut self) {
+    unsafe {
+      GL.delete_texture(self.inner);
+    }
+  }
+}
+
+/// 在目录中查找文件名(不含扩展名)为face的图片
+fn find_face(dir: &Path, face: &str) -> Result<PathBuf, Error> {
+  for entry in std::fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.is_file() && path.file_stem() == Some(face.as_ref()) {
+      return Ok(path);
+    }
+  }
+  Err(Error::LoadError(format!(
+    "{:?} 中缺少立方体贴图的面 {}",
+    dir, face
+  )))
+}
@@ -1,91 +1,344 @@
 use glow::HasContext;
+use image::{imageops, RgbaImage};
+use thiserror::Error;
 
 use crate::render_gl::debug;
+use crate::render_gl::sampler::SamplerDesc;
 use crate::GL;
 
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("帧缓冲不完整 ,原因:{reason} (0x{status:X})")]
+  Incomplete { status: u32, reason: &'static str },
+  #[error("颜色附件过多 {count} > {max}")]
+  TooManyColors { count: usize, max: usize },
+  #[error("多重采样的帧缓冲不支持深度纹理")]
+  MultisampledDepthTexture,
+}
+
+/// 颜色附件的内部格式
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorFormat {
+  Rgb8,
+  Rgba8,
+  // 浮点格式可保存超出[0, 1]的值
+  Rgba16F,
+  Rgba32F,
+  R32F,
+}
+impl ColorFormat {
+  /// (内部格式, 像素格式, 数据类型)
+  fn gl(self) -> (u32, u32, u32) {
+    match self {
+      ColorFormat::Rgb8 => (glow::RGB8, glow::RGB, glow::UNSIGNED_BYTE),
+      ColorFormat::Rgba8 => (glow::RGBA8, glow::RGBA, glow::UNSIGNED_BYTE),
+      ColorFormat::Rgba16F => (glow::RGBA16F, glow::RGBA, glow::FLOAT),
+      ColorFormat::Rgba32F => (glow::RGBA32F, glow::RGBA, glow::FLOAT),
+      ColorFormat::R32F => (glow::R32F, glow::RED, glow::FLOAT),
+    }
+  }
+}
+
+/// 深度(模板)附件的格式
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DepthFormat {
+  Depth32,
+  Depth32F,
+  Depth24Stencil8,
+}
+impl DepthFormat {
+  /// (内部格式, 像素格式, 数据类型, 附着点)
+  fn gl(self) -> (u32, u32, u32, u32) {
+    match self {
+      DepthFormat::Depth32 => (
+        glow::DEPTH_COMPONENT32,
+        glow::DEPTH_COMPONENT,
+        glow::UNSIGNED_INT,
+        glow::DEPTH_ATTACHMENT,
+      ),
+      DepthFormat::Depth32F => (
+        glow::DEPTH_COMPONENT32F,
+        glow::DEPTH_COMPONENT,
+        glow::FLOAT,
+        glow::DEPTH_ATTACHMENT,
+      ),
+      DepthFormat::Depth24Stencil8 => (
+        glow::DEPTH24_STENCIL8,
+        glow::DEPTH_STENCIL,
+        glow::UNSIGNED_INT_24_8,
+        glow::DEPTH_STENCIL_ATTACHMENT,
+      ),
+    }
+  }
+}
+
+/// 深度附件的存储方式
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Depth {
+  None,
+  // 只用于深度测试，无法采样
+  Renderbuffer(DepthFormat),
+  // 可在着色器中采样，如阴影贴图
+  Texture(DepthFormat),
+}
+
+/// 帧缓冲的附件描述，默认为一个RGB8颜色附件与一个32位深度渲染缓冲
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameBufferDesc {
+  pub width: i32,
+  pub height: i32,
+  // 按顺序对应 COLOR_ATTACHMENT0 + i
+  pub colors: Vec<ColorFormat>,
+  pub depth: Depth,
+  // 颜色与深度纹理的采样参数
+  pub sampler: SamplerDesc,
+  // 多重采样数，不大于1时关闭，超出MAX_SAMPLES时取上限
+  pub samples: i32,
+}
+impl FrameBufferDesc {
+  pub fn new(width: i32, height: i32) -> Self {
+    FrameBufferDesc {
+      width,
+      height,
+      colors: vec![ColorFormat::Rgb8],
+      depth: Depth::Renderbuffer(DepthFormat::Depth32),
+      sampler: SamplerDesc::screen(),
+      samples: 1,
+    }
+  }
+  /// 替换所有颜色附件，多个附件时片段着色器可同时输出到各附件(MRT)
+  pub fn colors(self, colors: &[ColorFormat]) -> Self {
+    FrameBufferDesc {
+      colors: colors.to_vec(),
+      ..self
+    }
+  }
+  /// 只有一个颜色附件
+  pub fn color(self, color: ColorFormat) -> Self {
+    self.colors(&[color])
+  }
+  pub fn depth(self, depth: Depth) -> Self {
+    FrameBufferDesc { depth, ..self }
+  }
+  pub fn sampler(self, sampler: SamplerDesc) -> Self {
+    FrameBufferDesc { sampler, ..self }
+  }
+  /// 开启多重采样时各附件为渲染缓冲，需`resolve`到单采样的纹理后才能采样
+  pub fn samples(self, samples: i32) -> Self {
+    FrameBufferDesc { samples, ..self }
+  }
+  pub fn build(&self) -> Result<FrameBuffer, Error> {
+    FrameBuffer::from_desc(self)
+  }
+}
+
+enum Attachment {
+  Texture(glow::Texture),
+  Renderbuffer(glow::Renderbuffer),
+}
+impl Attachment {
+  fn texture(&self) -> Option<glow::Texture> {
+    match self {
+      Attachment::Texture(texture) => Some(*texture),
+      Attachment::Renderbuffer(_) => None,
+    }
+  }
+}
+
+/// 帧缓冲及其附件，附件随帧缓冲一同释放
 pub struct FrameBuffer {
   pub width: i32,
   pub height: i32,
   // fbo
   pub inner: glow::Framebuffer,
-  pub texture: glow::Texture,
-  pub rbo: glow::Renderbuffer,
+  colors: Vec<Attachment>,
+  depth: Option<Attachment>,
+  desc: FrameBufferDesc,
+  // 多重采样时颜色附件的单采样副本
+  resolve: Option<Box<FrameBuffer>>,
 }
 impl Drop for FrameBuffer {
   fn drop(&mut self) {
     unsafe {
       GL.delete_framebuffer(self.inner);
+      for attachment in self.colors.iter().chain(&self.depth) {
+        match *attachment {
+          Attachment::Texture(texture) => GL.delete_texture(texture),
+          Attachment::Renderbuffer(rbo) => GL.delete_renderbuffer(rbo),
+        }
+      }
     }
   }
 }
 impl FrameBuffer {
-  pub fn new(width: i32, height: i32) -> Self {
+  /// 一个RGB8颜色附件与一个32位深度渲染缓冲
+  pub fn new(width: i32, height: i32) -> Result<Self, Error> {
+    FrameBufferDesc::new(width, height).build()
+  }
+
+  pub fn from_desc(desc: &FrameBufferDesc) -> Result<Self, Error> {
+    let max = unsafe { GL.get_parameter_i32(glow::MAX_COLOR_ATTACHMENTS) } as usize;
+    if desc.colors.len() > max {
+      return Err(Error::TooManyColors {
+        count: desc.colors.len(),
+        max,
+      });
+    }
+    let mut desc = desc.clone();
+    desc.samples = desc.samples.min(max_samples()).max(1);
+    let multisampled = desc.samples > 1;
+    if multisampled && matches!(desc.depth, Depth::Texture(_)) {
+      return Err(Error::MultisampledDepthTexture);
+    }
+    let resolve = if multisampled {
+      let resolve = desc.clone().depth(Depth::None).samples(1).build()?;
+      Some(Box::new(resolve))
+    } else {
+      None
+    };
+
     let fbo = unsafe { GL.create_framebuffer().unwrap() };
+    // 先构造出结构体，创建失败时由Drop释放已创建的附件
+    let mut frame_buffer = FrameBuffer {
+      width: desc.width,
+      height: desc.height,
+      inner: fbo,
+      colors: Vec::with_capacity(desc.colors.len()),
+      depth: None,
+      desc,
+      resolve,
+    };
+    let desc = &frame_buffer.desc;
+    frame_buffer.bind();
+    // 生成空白纹理(或多重采样的渲染缓冲)并attach到FBO上
+    for (i, format) in desc.colors.iter().enumerate() {
+      let (internal, format, ty) = format.gl();
+      let attachment = attach(
+        desc,
+        (internal, format, ty),
+        glow::COLOR_ATTACHMENT0 + i as u32,
+        multisampled,
+      );
+      frame_buffer.colors.push(attachment);
+    }
     unsafe {
-      GL.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+      if desc.colors.is_empty() {
+        // 只有深度附件，如阴影贴图
+        GL.draw_buffer(glow::NONE);
+        GL.read_buffer(glow::NONE);
+      } else {
+        GL.draw_buffers(&draw_buffers(desc.colors.len()));
+      }
+    }
+
+    // 渲染缓冲用于缓冲深度和模板信息，深度纹理则可在着色器中采样
+    frame_buffer.depth = match desc.depth {
+      Depth::None => None,
+      Depth::Texture(format) => {
+        let (internal, format, ty, attachment) = format.gl();
+        Some(attach(desc, (internal, format, ty), attachment, false))
+      }
+      Depth::Renderbuffer(format) => {
+        let (internal, format, ty, attachment) = format.gl();
+        Some(attach(desc, (internal, format, ty), attachment, true))
+      }
+    };
+
+    let status = unsafe { GL.check_framebuffer_status(glow::FRAMEBUFFER) };
+    frame_buffer.detach();
+    debug::check_error();
+    if status != glow::FRAMEBUFFER_COMPLETE {
+      return Err(Error::Incomplete {
+        status,
+        reason: incomplete_reason(status),
+      });
     }
-    let texture = unsafe { GL.create_texture().unwrap() };
-    // 生成空白纹理并attach到FBO上
+    Ok(frame_buffer)
+  }
+
+  /// 将多重采样的颜色附件解析(blit)到单采样的纹理，未开启多重采样时不做任何事
+  pub fn resolve(&self) {
+    let resolve = match &self.resolve {
+      Some(resolve) => resolve,
+      None => return,
+    };
     unsafe {
-      GL.bind_texture(glow::TEXTURE_2D, Some(texture));
-      GL.tex_parameter_i32(
-        glow::TEXTURE_2D,
-        glow::TEXTURE_MIN_FILTER,
-        glow::LINEAR as i32,
-      );
-      GL.tex_parameter_i32(
-        glow::TEXTURE_2D,
-        glow::TEXTURE_MAG_FILTER,
-        glow::LINEAR as i32,
-      );
-      GL.tex_image_2d(
-        glow::TEXTURE_2D,
-        0,
-        glow::RGB as i32,
-        width,
-        height,
-        0,
-        glow::RGB,
-        glow::UNSIGNED_BYTE,
-        None,
-      );
-      GL.bind_texture(glow::TEXTURE_2D, None);
+      GL.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.inner));
+      GL.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(resolve.inner));
+      // 每次只能解析一对读写缓冲
+      for i in 0..self.colors.len() as u32 {
+        GL.read_buffer(glow::COLOR_ATTACHMENT0 + i);
+        GL.draw_buffer(glow::COLOR_ATTACHMENT0 + i);
+        GL.blit_framebuffer(
+          0,
+          0,
+          self.width,
+          self.height,
+          0,
+          0,
+          resolve.width,
+          resolve.height,
+          glow::COLOR_BUFFER_BIT,
+          glow::NEAREST,
+        );
+      }
+      GL.draw_buffers(&draw_buffers(self.colors.len()));
+      GL.bind_framebuffer(glow::FRAMEBUFFER, None);
+    }
+  }
 
+  pub fn desc(&self) -> &FrameBufferDesc {
+    &self.desc
+  }
+  /// 实际的多重采样数，1为关闭
+  pub fn samples(&self) -> i32 {
+    self.desc.samples
+  }
+  /// 第一个颜色附件，没有颜色附件时panic
+  /// 多重采样时为解析后的纹理，需先调用`resolve`
+  pub fn texture(&self) -> glow::Texture {
+    self.color(0).unwrap()
+  }
+  /// 第i个颜色附件
+  pub fn color(&self, i: usize) -> Option<glow::Texture> {
+    match &self.resolve {
+      Some(resolve) => resolve.color(i),
+      None => self.colors.get(i).and_then(Attachment::texture),
+    }
+  }
+  /// 以纹理存储的深度附件
+  pub fn depth_texture(&self) -> Option<glow::Texture> {
+    self.depth.as_ref().and_then(Attachment::texture)
+  }
+
+  /// 读取颜色附件，行序翻转为自上而下
+  /// 多重采样时读取解析后的纹理，需先调用`resolve`
+  pub fn read_pixels(&self) -> RgbaImage {
+    if let Some(resolve) = &self.resolve {
+      return resolve.read_pixels();
+    }
+    self.bind();
+    unsafe {
+      GL.read_buffer(glow::COLOR_ATTACHMENT0);
+    }
+    let image = read_rgba(self.width, self.height);
+    self.detach();
+    image
+  }
+
+  /// 替换颜色附件，用于渲染到立方体贴图的某一面或某一层mip
+  /// target: TEXTURE_2D 或 TEXTURE_CUBE_MAP_POSITIVE_X + i
+  /// 原有的附件仍由帧缓冲持有
+  pub fn attach_color(&self, target: u32, texture: glow::Texture, level: i32) {
+    self.bind();
+    unsafe {
       GL.framebuffer_texture_2d(
         glow::FRAMEBUFFER,
         glow::COLOR_ATTACHMENT0,
-        glow::TEXTURE_2D,
+        target,
         Some(texture),
-        0,
-      );
-    }
-    // 生成render buffer以缓冲深度和模板信息
-    let rbo = unsafe { GL.create_renderbuffer().unwrap() };
-    unsafe {
-      GL.bind_renderbuffer(glow::RENDERBUFFER, Some(rbo));
-      // 通过glRenderbufferStorage API给RBO创建、初始化存储空间
-      GL.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH_COMPONENT32, width, height);
-      // glFramebufferRenderbuffer API 将指定的RBO关联到GPU当前的FBO上。
-      GL.framebuffer_renderbuffer(
-        glow::FRAMEBUFFER,
-        glow::DEPTH_ATTACHMENT,
-        glow::RENDERBUFFER,
-        Some(rbo),
+        level,
       );
-      if GL.check_framebuffer_status(glow::FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
-        println!("帧缓冲创建失败");
-        debug::check_error();
-      }
-      GL.bind_renderbuffer(glow::RENDERBUFFER, None);
-      GL.bind_framebuffer(glow::FRAMEBUFFER, None);
-      debug::check_error();
-    }
-    Self {
-      inner: fbo,
-      width,
-      height,
-      texture,
-      rbo,
     }
   }
 
@@ -100,3 +353,109 @@ impl FrameBuffer {
     }
   }
 }
+
+/// 当前GL实现支持的最大多重采样数
+pub fn max_samples() -> i32 {
+  unsafe { GL.get_parameter_i32(glow::MAX_SAMPLES) }
+}
+
+fn draw_buffers(count: usize) -> Vec<u32> {
+  (0..count as u32)
+    .map(|i| glow::COLOR_ATTACHMENT0 + i)
+    .collect()
+}
+
+/// 创建与帧缓冲同样大小的附件并attach到当前绑定的FBO上
+/// gl: (内部格式, 像素格式, 数据类型)，renderbuffer为false时创建空白纹理
+fn attach(
+  desc: &FrameBufferDesc,
+  (internal, format, ty): (u32, u32, u32),
+  attachment: u32,
+  renderbuffer: bool,
+) -> Attachment {
+  unsafe {
+    if renderbuffer {
+      let rbo = GL.create_renderbuffer().unwrap();
+      GL.bind_renderbuffer(glow::RENDERBUFFER, Some(rbo));
+      // 通过glRenderbufferStorage API给RBO创建、初始化存储空间
+      // 采样数为0时与单采样的纹理附件一致，为1时可能分配多重采样的存储
+      let samples = if desc.samples > 1 { desc.samples } else { 0 };
+      GL.renderbuffer_storage_multisample(
+        glow::RENDERBUFFER,
+        samples,
+        internal,
+        desc.width,
+        desc.height,
+      );
+      // glFramebufferRenderbuffer API 将指定的RBO关联到GPU当前的FBO上。
+      GL.framebuffer_renderbuffer(glow::FRAMEBUFFER, attachment, glow::RENDERBUFFER, Some(rbo));
+      GL.bind_renderbuffer(glow::RENDERBUFFER, None);
+      return Attachment::Renderbuffer(rbo);
+    }
+    let texture = GL.create_texture().unwrap();
+    GL.bind_texture(glow::TEXTURE_2D, Some(texture));
+    desc.sampler.mipmap(None).apply_to_texture(glow::TEXTURE_2D);
+    GL.tex_image_2d(
+      glow::TEXTURE_2D,
+      0,
+      internal as i32,
+      desc.width,
+      desc.height,
+      0,
+      format,
+      ty,
+      None,
+    );
+    GL.bind_texture(glow::TEXTURE_2D, None);
+    GL.framebuffer_texture_2d(
+      glow::FRAMEBUFFER,
+      attachment,
+      glow::TEXTURE_2D,
+      Some(texture),
+      0,
+    );
+    Attachment::Texture(texture)
+  }
+}
+
+fn incomplete_reason(status: u32) -> &'static str {
+  match status {
+    glow::FRAMEBUFFER_UNDEFINED => "默认帧缓冲不存在",
+    glow::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "附件不完整",
+    glow::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "没有任何附件",
+    glow::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "绘制缓冲指向了不存在的附件",
+    glow::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "读缓冲指向了不存在的附件",
+    glow::FRAMEBUFFER_UNSUPPORTED => "驱动不支持该附件格式组合",
+    glow::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "各附件的采样数不一致",
+    glow::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "各附件的层数不一致",
+    _ => "未知原因",
+  }
+}
+
+/// 读取默认帧缓冲(窗口)的后缓冲区，需在交换缓冲区之前调用
+pub fn read_screen(width: i32, height: i32) -> RgbaImage {
+  unsafe {
+    GL.bind_framebuffer(glow::FRAMEBUFFER, None);
+    GL.read_buffer(glow::BACK);
+  }
+  read_rgba(width, height)
+}
+
+// 从当前绑定的读缓冲读取RGBA像素，OpenGL的行序自下而上，需要翻转
+fn read_rgba(width: i32, height: i32) -> RgbaImage {
+  let mut pixels = vec![0u8; (width * height * 4) as usize];
+  unsafe {
+    GL.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+    GL.read_pixels(
+      0,
+      0,
+      width,
+      height,
+      glow::RGBA,
+      glow::UNSIGNED_BYTE,
+      glow::PixelPackData::Slice(&mut pixels),
+    );
+  }
+  let image = RgbaImage::from_raw(width as u32, height as u32, pixels).unwrap();
+  imageops::flip_vertical(&image)
+}
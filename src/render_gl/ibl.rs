@@ -0,0 +1,437 @@
+use std::cell::Cell;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::Context;
+use glow::HasContext;
+use image::codecs::hdr::HdrEncoder;
+use image::{DynamicImage, Rgb, Rgb32FImage, Rgba32FImage};
+use na::{Matrix4, Point3, Vector3};
+
+use crate::render_gl::buffer::{self, UniformBuffer, VertexArray};
+use crate::render_gl::cubemap::Cubemap;
+use crate::render_gl::frame_buffer::FrameBuffer;
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::sampler::{Filter, SamplerDesc, Wrap};
+use crate::render_gl::texture::{load_image, Texture};
+use crate::render_gl::{self, skybox, Program};
+use crate::resources::Resources;
+use crate::GL;
+
+/// 着色器中环境光照uniform块的名称，见 shaders/common/ibl.glsl
+pub const ENVIRONMENT_BLOCK: &str = "Environment";
+// 预计算结果使用的纹理单元，避开场景自身使用的低序号单元
+pub const IRRADIANCE_UNIT: u32 = 8;
+pub const PREFILTER_UNIT: u32 = 9;
+pub const BRDF_UNIT: u32 = 10;
+
+// 预计算结果的缓存目录，相对于工作目录
+const CACHE_DIR: &str = "cache/ibl";
+// 预计算的着色器或参数改变后需递增，使旧的缓存失效
+const CACHE_VERSION: u32 = 1;
+const ENVIRONMENT_SIZE: u32 = 512;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTER_SIZE: u32 = 128;
+// 粗糙度0~1均分到各层mip
+const PREFILTER_LEVELS: u32 = 5;
+const BRDF_SIZE: u32 = 512;
+
+/// 与着色器中 std140 布局的Environment块一一对应
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+struct EnvironmentBlock {
+  intensity: f32,
+  // 预过滤贴图的最大mip层级
+  max_lod: f32,
+  // 开启时为1，光源的环境光分量由IBL取代
+  enabled: f32,
+  _padding: f32,
+}
+
+/// 由HDR全景图预计算的环境光照(IBL)
+pub struct Environment {
+  irradiance: Cubemap,
+  prefiltered: Cubemap,
+  brdf_lut: Texture,
+  buffer: UniformBuffer,
+  binding: u32,
+  intensity: Cell<f32>,
+  enabled: Cell<bool>,
+}
+
+impl Environment {
+  /// name: 等距柱状投影(equirectangular)的.hdr全景图
+  /// 预计算结果缓存在 cache/ibl 下，全景图修改后会重新计算
+  pub fn from_res(res: &Resources, name: &str) -> Result<Environment, anyhow::Error> {
+    let path = res.resolve_path(name);
+    let cache = cache_dir(&path)?;
+    let (irradiance, prefiltered) = match load_maps(&cache) {
+      Some(maps) => maps,
+      None => {
+        let (irradiance, prefiltered) = precompute_maps(res, name)?;
+        if let Err(e) = save_maps(&cache, &irradiance, &prefiltered) {
+          println!("{:?}", e);
+        }
+        (irradiance, prefiltered)
+      }
+    };
+    // BRDF查找表与环境无关，所有全景图共用一份
+    let brdf_path = Path::new(CACHE_DIR).join(format!("brdf-v{}.hdr", CACHE_VERSION));
+    let brdf_lut = match load_hdr(&brdf_path, BRDF_SIZE) {
+      Some(lut) => {
+        Texture::from_image_with(DynamicImage::ImageRgb32F(lut), &SamplerDesc::screen())?
+      }
+      None => {
+        let lut = precompute_brdf(res)?;
+        if let Err(e) = save_hdr(&brdf_path, &read_texture(&lut, BRDF_SIZE)) {
+          println!("{:?}", e);
+        }
+        lut
+      }
+    };
+
+    let buffer = UniformBuffer::new();
+    buffer.bind();
+    buffer.allocate(std::mem::size_of::<EnvironmentBlock>(), glow::DYNAMIC_DRAW);
+    buffer.unbind();
+    let environment = Environment {
+      irradiance,
+      prefiltered,
+      brdf_lut,
+      buffer,
+      binding: buffer::uniform_binding(ENVIRONMENT_BLOCK)?,
+      intensity: Cell::new(1.0),
+      enabled: Cell::new(true),
+    };
+    environment.upload();
+    Ok(environment)
+  }
+
+  pub fn intensity(&self) -> f32 {
+    self.intensity.get()
+  }
+  pub fn set_intensity(&self, intensity: f32) {
+    self.intensity.set(intensity);
+    self.upload();
+  }
+  pub fn enabled(&self) -> bool {
+    self.enabled.get()
+  }
+  /// 关闭时场景使用光源自身的环境光分量
+  pub fn set_enabled(&self, enabled: bool) {
+    self.enabled.set(enabled);
+    self.upload();
+  }
+  fn upload(&self) {
+    let block = EnvironmentBlock {
+      intensity: self.intensity.get(),
+      max_lod: (PREFILTER_LEVELS - 1) as f32,
+      enabled: self.enabled.get() as u32 as f32,
+      _padding: 0.0,
+    };
+    self.buffer.bind();
+    self.buffer.sub_data(0, &[block]);
+    self.buffer.unbind();
+  }
+
+  /// 将预计算结果绑定到约定的纹理单元，需在场景渲染之前调用
+  pub fn bind(&self) {
+    self.buffer.bind_base(self.binding);
+    unsafe {
+      GL.active_texture(glow::TEXTURE0 + IRRADIANCE_UNIT);
+      self.irradiance.bind();
+      GL.active_texture(glow::TEXTURE0 + PREFILTER_UNIT);
+      self.prefiltered.bind();
+      GL.active_texture(glow::TEXTURE0 + BRDF_UNIT);
+      self.brdf_lut.bind();
+      GL.active_texture(glow::TEXTURE0);
+    }
+  }
+
+  /// 为导入了 common/ibl.glsl 的着色程序设置uniform块与纹理单元
+  pub fn bind_program(program: &Program) -> Result<(), render_gl::Error> {
+    program.bind_uniform_block(ENVIRONMENT_BLOCK)?;
+    program.upload_texture_slot("irradianceMap", IRRADIANCE_UNIT as i32)?;
+    program.upload_texture_slot("prefilterMap", PREFILTER_UNIT as i32)?;
+    program.upload_texture_slot("brdfLut", BRDF_UNIT as i32)
+  }
+}
+
+/// 缓存目录名包含全景图的大小与修改时间
+fn cache_dir(path: &Path) -> Result<PathBuf, anyhow::Error> {
+  let metadata = std::fs::metadata(path).with_context(|| format!("全景图读取失败 {:?}", path))?;
+  let modified = metadata
+    .modified()
+    .ok()
+    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+    .map(|d| d.as_secs())
+    .unwrap_or_default();
+  let stem = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  Ok(PathBuf::from(CACHE_DIR).join(format!(
+    "{}-{}-{:x}-v{}",
+    stem,
+    metadata.len(),
+    modified,
+    CACHE_VERSION
+  )))
+}
+
+fn irradiance_sampler() -> SamplerDesc {
+  SamplerDesc::new().wrap(Wrap::ClampToEdge).mipmap(None)
+}
+fn prefilter_sampler() -> SamplerDesc {
+  SamplerDesc::new()
+    .wrap(Wrap::ClampToEdge)
+    .mipmap(Some(Filter::Linear))
+}
+
+fn irradiance_path(cache: &Path, face: u32) -> PathBuf {
+  cache.join(format!("irradiance-{}.hdr", face))
+}
+fn prefilter_path(cache: &Path, level: u32, face: u32) -> PathBuf {
+  cache.join(format!("prefilter-{}-{}.hdr", level, face))
+}
+
+/// 读取缓存，任一文件缺失或尺寸不符时返回None
+fn load_maps(cache: &Path) -> Option<(Cubemap, Cubemap)> {
+  let irradiance = Cubemap::new_hdr(IRRADIANCE_SIZE, 1, &irradiance_sampler());
+  for face in 0..6 {
+    let image = load_hdr(&irradiance_path(cache, face), IRRADIANCE_SIZE)?;
+    irradiance.write_face(face, 0, &image);
+  }
+  let prefiltered = Cubemap::new_hdr(PREFILTER_SIZE, PREFILTER_LEVELS, &prefilter_sampler());
+  for level in 0..PREFILTER_LEVELS {
+    for face in 0..6 {
+      let image = load_hdr(&prefilter_path(cache, level, face), PREFILTER_SIZE >> level)?;
+      prefiltered.write_face(face, level, &image);
+    }
+  }
+  Some((irradiance, prefiltered))
+}
+
+fn save_maps(
+  cache: &Path,
+  irradiance: &Cubemap,
+  prefiltered: &Cubemap,
+) -> Result<(), anyhow::Error> {
+  std::fs::create_dir_all(cache).with_context(|| format!("IBL缓存目录创建失败 {:?}", cache))?;
+  for face in 0..6 {
+    save_hdr(
+      &irradiance_path(cache, face),
+      &irradiance.read_face(face, 0),
+    )?;
+    for level in 0..PREFILTER_LEVELS {
+      save_hdr(
+        &prefilter_path(cache, level, face),
+        &prefiltered.read_face(face, level),
+      )?;
+    }
+  }
+  Ok(())
+}
+
+fn load_hdr(path: &Path, size: u32) -> Option<Rgb32FImage> {
+  let image = load_image(path).ok()?.into_rgb32f();
+  (image.dimensions() == (size, size)).then_some(image)
+}
+
+fn save_hdr(path: &Path, image: &Rgb32FImage) -> Result<(), anyhow::Error> {
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir)?;
+  }
+  let file = File::create(path).with_context(|| format!("IBL缓存写入失败 {:?}", path))?;
+  let pixels: Vec<Rgb<f32>> = image.pixels().copied().collect();
+  HdrEncoder::new(BufWriter::new(file))
+    .encode(&pixels, image.width() as usize, image.height() as usize)
+    .with_context(|| format!("IBL缓存写入失败 {:?}", path))?;
+  Ok(())
+}
+
+/// 读取二维浮点纹理的RGB通道
+fn read_texture(texture: &Texture, size: u32) -> Rgb32FImage {
+  let mut pixels = vec![0f32; (size * size * 3) as usize];
+  texture.bind();
+  unsafe {
+    GL.pixel_store_i32(glow::PACK_ALIGNMENT, 4);
+    GL.get_tex_image(
+      glow::TEXTURE_2D,
+      0,
+      glow::RGB,
+      glow::FLOAT,
+      glow::PixelPackData::Slice(another::any_as_u8_slice_mut(&mut pixels)),
+    );
+  }
+  Rgb32FImage::from_raw(size, size, pixels).unwrap()
+}
+
+/// 预计算过程中的渲染状态，结束时恢复视口、混合与深度测试
+struct Pass {
+  frame_buffer: FrameBuffer,
+  viewport: [i32; 4],
+  blend: bool,
+  depth_test: bool,
+}
+impl Pass {
+  fn begin() -> Result<Pass, anyhow::Error> {
+    let frame_buffer = FrameBuffer::new(ENVIRONMENT_SIZE as i32, ENVIRONMENT_SIZE as i32)?;
+    let mut viewport = [0; 4];
+    let (blend, depth_test) = unsafe {
+      GL.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+      let state = (GL.is_enabled(glow::BLEND), GL.is_enabled(glow::DEPTH_TEST));
+      GL.disable(glow::BLEND);
+      GL.disable(glow::DEPTH_TEST);
+      state
+    };
+    Ok(Pass {
+      frame_buffer,
+      viewport,
+      blend,
+      depth_test,
+    })
+  }
+
+  /// 将立方体的六个面分别渲染到target的第level层mip
+  fn render_faces(
+    &self,
+    program: &Program,
+    cube: &Mesh,
+    target: &Cubemap,
+    level: u32,
+  ) -> Result<(), render_gl::Error> {
+    // 各面的朝向与上方向，与 TEXTURE_CUBE_MAP_POSITIVE_X + i 的约定一致
+    let views = [
+      (Vector3::x(), -Vector3::y()),
+      (-Vector3::x(), -Vector3::y()),
+      (Vector3::y(), Vector3::z()),
+      (-Vector3::y(), -Vector3::z()),
+      (Vector3::z(), -Vector3::y()),
+      (-Vector3::z(), -Vector3::y()),
+    ];
+    let proj = Matrix4::new_perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 10.0);
+    let size = (target.size() >> level).max(1) as i32;
+    unsafe {
+      GL.viewport(0, 0, size, size);
+    }
+    program.set_used();
+    for (face, (toward, up)) in views.iter().enumerate() {
+      let view = Matrix4::look_at_rh(&Point3::origin(), &Point3::from(*toward), up);
+      program.upload_mat4("view_proj", &(proj * view))?;
+      self.frame_buffer.attach_color(
+        glow::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+        target.raw(),
+        level as i32,
+      );
+      unsafe {
+        GL.clear(glow::COLOR_BUFFER_BIT);
+      }
+      cube.draw();
+    }
+    Ok(())
+  }
+
+  fn end(self) {
+    self.frame_buffer.detach();
+    let [x, y, w, h] = self.viewport;
+    unsafe {
+      GL.viewport(x, y, w, h);
+      set_enabled(glow::BLEND, self.blend);
+      set_enabled(glow::DEPTH_TEST, self.depth_test);
+    }
+  }
+}
+
+unsafe fn set_enabled(capability: u32, enabled: bool) {
+  if enabled {
+    GL.enable(capability);
+  } else {
+    GL.disable(capability);
+  }
+}
+
+/// 全景图 -> 环境立方体贴图 -> 辐照度图与预过滤图
+fn precompute_maps(res: &Resources, name: &str) -> Result<(Cubemap, Cubemap), anyhow::Error> {
+  let equirect = Texture::from_res_with(res, name, &SamplerDesc::new().mipmap(None))?;
+  let cube = skybox::cube_mesh();
+  let pass = Pass::begin()?;
+
+  let environment = Cubemap::new_hdr(ENVIRONMENT_SIZE, 1, &prefilter_sampler());
+  let program = Program::from_stages(
+    res,
+    "shaders/ibl/equirect",
+    stages(&["cubemap.vert", "equirect.frag"]),
+  )?;
+  program.upload_texture_slot("equirectMap", 0)?;
+  unsafe {
+    GL.active_texture(glow::TEXTURE0);
+  }
+  equirect.bind();
+  pass.render_faces(&program, &cube, &environment, 0)?;
+  // 供卷积时采样较低的mip层级
+  environment.generate_mipmap();
+
+  let irradiance = Cubemap::new_hdr(IRRADIANCE_SIZE, 1, &irradiance_sampler());
+  let program = Program::from_stages(
+    res,
+    "shaders/ibl/irradiance",
+    stages(&["cubemap.vert", "irradiance.frag"]),
+  )?;
+  program.upload_texture_slot("environmentMap", 0)?;
+  environment.bind();
+  pass.render_faces(&program, &cube, &irradiance, 0)?;
+
+  let prefiltered = Cubemap::new_hdr(PREFILTER_SIZE, PREFILTER_LEVELS, &prefilter_sampler());
+  let program = Program::from_stages(
+    res,
+    "shaders/ibl/prefilter",
+    stages(&["cubemap.vert", "prefilter.frag"]),
+  )?;
+  program.upload_texture_slot("environmentMap", 0)?;
+  program.upload_float("resolution", ENVIRONMENT_SIZE as f32)?;
+  environment.bind();
+  for level in 0..PREFILTER_LEVELS {
+    let roughness = level as f32 / (PREFILTER_LEVELS - 1) as f32;
+    program.upload_float("roughness", roughness)?;
+    pass.render_faces(&program, &cube, &prefiltered, level)?;
+  }
+  environment.detach();
+  program.detach();
+  pass.end();
+  Ok((irradiance, prefiltered))
+}
+
+fn precompute_brdf(res: &Resources) -> Result<Texture, anyhow::Error> {
+  let lut = Texture::from_image_with(
+    DynamicImage::ImageRgba32F(Rgba32FImage::new(BRDF_SIZE, BRDF_SIZE)),
+    &SamplerDesc::screen(),
+  )?;
+  let program = Program::from_stages(res, "shaders/ibl/brdf", stages(&["brdf.vert", "brdf.frag"]))?;
+  let pass = Pass::begin()?;
+  pass
+    .frame_buffer
+    .attach_color(glow::TEXTURE_2D, lut.raw(), 0);
+  // 全屏三角形由顶点序号生成，只需绑定一个空的VAO
+  let vao = VertexArray::new();
+  program.set_used();
+  vao.bind();
+  unsafe {
+    GL.viewport(0, 0, BRDF_SIZE as i32, BRDF_SIZE as i32);
+    GL.clear(glow::COLOR_BUFFER_BIT);
+    GL.draw_arrays(glow::TRIANGLES, 0, 3);
+  }
+  vao.unbind();
+  program.detach();
+  pass.end();
+  Ok(lut)
+}
+
+fn stages(files: &[&str]) -> Vec<String> {
+  files
+    .iter()
+    .map(|file| format!("shaders/ibl/{}", file))
+    .collect()
+}
@@ -0,0 +1,59 @@
+use std::fmt::Debug;
+
+use glow::HasContext;
+
+use crate::render_gl::buffer;
+use crate::GL;
+
+/// 已上传至GPU的索引网格
+pub struct Mesh {
+  _vbo: buffer::ArrayBuffer,
+  _ebo: buffer::ElementArrayBuffer,
+  vao: buffer::VertexArray,
+  index_count: i32,
+}
+
+impl Mesh {
+  /// 上传顶点与索引数据
+  /// attrib_pointers: 顶点类型由`VertexAttribPointers`派生出的`vertex_attrib_pointers`
+  pub fn new<V>(vertices: &[V], indices: &[u32], attrib_pointers: fn()) -> Mesh
+  where
+    V: Debug,
+  {
+    let vbo = buffer::ArrayBuffer::new();
+    vbo.bind();
+    vbo.static_draw_data(vertices);
+    vbo.unbind();
+    let ebo = buffer::ElementArrayBuffer::new();
+    ebo.bind();
+    ebo.static_draw_data(indices);
+    ebo.unbind();
+    let vao = buffer::VertexArray::new();
+
+    vao.bind();
+    vbo.bind();
+    ebo.bind();
+    attrib_pointers();
+    // 注意这里有一个自动绑定机制
+    vao.unbind();
+
+    Mesh {
+      _vbo: vbo,
+      _ebo: ebo,
+      vao,
+      index_count: indices.len() as i32,
+    }
+  }
+
+  pub fn index_count(&self) -> i32 {
+    self.index_count
+  }
+
+  pub fn draw(&self) {
+    self.vao.bind();
+    unsafe {
+      GL.draw_elements(glow::TRIANGLES, self.index_count, glow::UNSIGNED_INT, 0);
+    }
+    self.vao.unbind();
+  }
+}
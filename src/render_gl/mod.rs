@@ -1,12 +1,28 @@
+pub mod atlas;
+pub mod bloom;
 pub mod buffer;
+mod compressed;
+mod compute;
+pub mod cubemap;
 pub mod data;
 pub mod debug;
 pub mod frame_buffer;
+pub mod ibl;
+pub mod mesh;
 pub mod offscreen;
+pub mod post;
+pub mod preprocess;
+pub mod reload;
+pub mod sampler;
 mod shader;
+pub mod skybox;
 pub mod texture;
+pub mod texture_array;
+pub mod tonemap;
+pub mod uniform;
 mod viewport;
 
+pub use self::compute::ComputeProgram;
 pub use self::shader::{Error, Program, Shader};
 pub use self::viewport::Viewport;
 
@@ -1,34 +1,76 @@
 use std::sync::RwLock;
 
 use glow::HasContext;
+use image::RgbaImage;
 
+use crate::render_gl::bloom::Bloom;
 use crate::render_gl::buffer;
 use crate::render_gl::data::*;
 use crate::render_gl::debug::check_error;
-use crate::render_gl::frame_buffer::FrameBuffer;
+use crate::render_gl::frame_buffer::{ColorFormat, FrameBuffer, FrameBufferDesc};
+use crate::render_gl::post::{self, PostChain};
+use crate::render_gl::tonemap::ToneMapper;
 use crate::resources::Resources;
 use crate::{render_gl, GL};
 
+/// 场景先渲染到浮点帧缓冲，经泛光与色调映射后再执行后处理链，最后绘制到窗口
 pub struct OffScreen {
   pub frame_buffer: RwLock<FrameBuffer>,
-  pub render: RwLock<Render>,
-  res: Resources,
+  pub post: RwLock<PostChain>,
+  pub bloom: RwLock<Bloom>,
+  pub tonemap: RwLock<ToneMapper>,
+  pub render: Render,
 }
 impl OffScreen {
   pub fn new(res: &Resources, width: i32, height: i32) -> anyhow::Result<Self> {
-    let frame_buffer = FrameBuffer::new(width, height);
-    let render = Render::new(&res, &frame_buffer)?;
+    let frame_buffer = Self::create_frame_buffer(width, height, 1)?;
+    let post = PostChain::new(res, &Self::post_desc(width, height))?;
+    let bloom = Bloom::new(res, width, height)?;
+    let tonemap = ToneMapper::new(res, width, height)?;
+    let render = Render::new(res)?;
     Ok(Self {
       frame_buffer: RwLock::new(frame_buffer),
-      render: RwLock::new(render),
-      res: res.clone(),
+      post: RwLock::new(post),
+      bloom: RwLock::new(bloom),
+      tonemap: RwLock::new(tonemap),
+      render,
     })
   }
+  fn create_frame_buffer(width: i32, height: i32, samples: i32) -> anyhow::Result<FrameBuffer> {
+    // 保留超出[0, 1]的亮度，由色调映射压缩
+    Ok(
+      FrameBufferDesc::new(width, height)
+        .color(ColorFormat::Rgba16F)
+        .samples(samples)
+        .build()?,
+    )
+  }
+  /// 后处理链的卷积、反相等效果针对[0, 1]的画面，在色调映射之后执行
+  fn post_desc(width: i32, height: i32) -> FrameBufferDesc {
+    FrameBufferDesc::new(width, height).color(ColorFormat::Rgba8)
+  }
   pub fn resize(&self, width: i32, height: i32) -> anyhow::Result<()> {
     let mut frame_buffer = self.frame_buffer.write().unwrap();
-    *frame_buffer = FrameBuffer::new(width, height);
-    let mut render = self.render.write().unwrap();
-    *render = Render::new(&self.res, &frame_buffer)?;
+    let samples = frame_buffer.samples();
+    *frame_buffer = Self::create_frame_buffer(width, height, samples)?;
+    self
+      .post
+      .write()
+      .unwrap()
+      .resize(&Self::post_desc(width, height))?;
+    self.bloom.write().unwrap().resize(width, height)?;
+    self.tonemap.write().unwrap().resize(width, height)?;
+    Ok(())
+  }
+  /// 场景帧缓冲的多重采样数，1为关闭
+  pub fn samples(&self) -> i32 {
+    self.frame_buffer.read().unwrap().samples()
+  }
+  /// 超出MAX_SAMPLES时取上限
+  pub fn set_samples(&self, samples: i32) -> anyhow::Result<()> {
+    let mut frame_buffer = self.frame_buffer.write().unwrap();
+    let (width, height) = (frame_buffer.width, frame_buffer.height);
+    *frame_buffer = Self::create_frame_buffer(width, height, samples)?;
     Ok(())
   }
   pub fn bind(&self) {
@@ -37,8 +79,36 @@ impl OffScreen {
   pub fn detach(&self) {
     self.frame_buffer.read().unwrap().detach();
   }
+  /// 解析多重采样后对场景帧缓冲依次执行泛光、色调映射与后处理链，需在场景渲染之后调用
+  pub fn post_process(&self) -> Result<(), post::Error> {
+    let frame_buffer = self.frame_buffer.read().unwrap();
+    frame_buffer.resolve();
+    let bloom = self.bloom.read().unwrap();
+    let texture = match bloom.apply(frame_buffer.texture())? {
+      Some(output) => output.texture(),
+      None => frame_buffer.texture(),
+    };
+    let tonemap = self.tonemap.read().unwrap();
+    let texture = tonemap.apply(texture)?.texture();
+    self.post.read().unwrap().apply(texture)?;
+    Ok(())
+  }
+  /// 最终画面所在的帧缓冲，没有开启的后处理步骤时为色调映射的结果
+  fn with_output<R>(&self, f: impl FnOnce(&FrameBuffer) -> R) -> R {
+    let post = self.post.read().unwrap();
+    match post.output() {
+      Some(output) => f(output),
+      None => f(self.tonemap.read().unwrap().output()),
+    }
+  }
+  /// 读取最终画面，行序自上而下
+  pub fn read_pixels(&self) -> RgbaImage {
+    self.with_output(|output| output.read_pixels())
+  }
+  /// 将最终画面绘制到窗口
   pub fn render_output(&self) {
-    self.render.read().unwrap().render();
+    let texture = self.with_output(|output| output.texture());
+    self.render.render(texture);
   }
 }
 
@@ -56,11 +126,10 @@ pub struct Render {
   _vbo: buffer::ArrayBuffer,
   _ebo: buffer::ElementArrayBuffer,
   vao: buffer::VertexArray,
-  texture: glow::Texture,
 }
 
 impl Render {
-  pub fn new(res: &Resources, frame_buffer: &FrameBuffer) -> Result<Self, anyhow::Error> {
+  pub fn new(res: &Resources) -> Result<Self, anyhow::Error> {
     let program = render_gl::Program::from_res(res, "shaders/offscreen")?;
 
     let vertices: Vec<Vertex> = vec![
@@ -106,16 +175,15 @@ impl Render {
       _vbo: vbo,
       _ebo: ebo,
       vao,
-      texture: frame_buffer.texture,
     })
   }
-  pub fn render(&self) -> Option<()> {
+  pub fn render(&self, texture: glow::Texture) -> Option<()> {
     check_error();
     self.program.set_used();
     self.vao.bind();
     unsafe {
       GL.active_texture(glow::TEXTURE0);
-      GL.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+      GL.bind_texture(glow::TEXTURE_2D, Some(texture));
       GL.draw_elements(glow::TRIANGLES, 6, glow::UNSIGNED_INT, 0);
     }
     self.vao.unbind();
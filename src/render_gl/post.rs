@@ -0,0 +1,253 @@
+use std::cell::Cell;
+use std::ops::RangeInclusive;
+
+use glow::HasContext;
+use thiserror::Error;
+
+use crate::render_gl::buffer::VertexArray;
+use crate::render_gl::frame_buffer::{self, Depth, FrameBuffer, FrameBufferDesc};
+use crate::render_gl::sampler::{Sampler, SamplerDesc};
+use crate::render_gl::{shader, Program};
+use crate::resources::Resources;
+use crate::GL;
+
+#[derive(Debug, Error)]
+pub enum Error {
+  #[error("后处理着色器加载失败")]
+  Shader(#[from] shader::Error),
+  #[error("后处理帧缓冲创建失败")]
+  FrameBuffer(#[from] frame_buffer::Error),
+}
+
+/// 内置的后处理效果
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Effect {
+  Invert,
+  Grayscale,
+  Blur,
+  Sharpen,
+  EdgeDetect,
+}
+impl Effect {
+  pub const ALL: [Effect; 5] = [
+    Effect::Invert,
+    Effect::Grayscale,
+    Effect::Blur,
+    Effect::Sharpen,
+    Effect::EdgeDetect,
+  ];
+  pub fn name(self) -> &'static str {
+    match self {
+      Effect::Invert => "反相",
+      Effect::Grayscale => "灰度",
+      Effect::Blur => "模糊",
+      Effect::Sharpen => "锐化",
+      Effect::EdgeDetect => "边缘检测",
+    }
+  }
+  fn kernel(self) -> Option<[f32; 9]> {
+    match self {
+      Effect::Invert | Effect::Grayscale => None,
+      Effect::Blur => Some([
+        1.0 / 16.0,
+        2.0 / 16.0,
+        1.0 / 16.0,
+        2.0 / 16.0,
+        4.0 / 16.0,
+        2.0 / 16.0,
+        1.0 / 16.0,
+        2.0 / 16.0,
+        1.0 / 16.0,
+      ]),
+      Effect::Sharpen => Some([-1.0, -1.0, -1.0, -1.0, 9.0, -1.0, -1.0, -1.0, -1.0]),
+      Effect::EdgeDetect => Some([1.0, 1.0, 1.0, 1.0, -8.0, 1.0, 1.0, 1.0, 1.0]),
+    }
+  }
+  pub fn pass(self, res: &Resources) -> Result<PostPass, Error> {
+    let shader = match self {
+      Effect::Invert => "shaders/post/invert.frag",
+      Effect::Grayscale => "shaders/post/grayscale.frag",
+      _ => "shaders/post/kernel.frag",
+    };
+    let pass = PostPass::new(res, self.name(), shader)?.param("strength", 1.0, 0.0..=1.0);
+    Ok(match self.kernel() {
+      Some(kernel) => pass
+        .constant("kernel", &kernel)
+        .param("offset", 1.0, 0.5..=4.0),
+      None => pass,
+    })
+  }
+}
+
+/// 可在界面中调节的float uniform
+#[derive(Clone, Debug)]
+pub struct Param {
+  pub name: String,
+  pub value: f32,
+  pub range: RangeInclusive<f32>,
+}
+
+/// 一个全屏的后处理步骤，输入纹理为 sampler2D frame
+pub struct PostPass {
+  name: String,
+  program: Program,
+  pub enabled: bool,
+  pub params: Vec<Param>,
+  // 不在界面中显示的float数组，如卷积核
+  constants: Vec<(String, Vec<f32>)>,
+}
+impl PostPass {
+  /// frag: 片段着色器的资源名，顶点着色器使用 shaders/post/fullscreen.vert
+  pub fn new(res: &Resources, name: &str, frag: &str) -> Result<PostPass, Error> {
+    let program = Program::from_stages(
+      res,
+      frag.trim_end_matches(".frag"),
+      vec!["shaders/post/fullscreen.vert".to_string(), frag.to_string()],
+    )?;
+    program.upload_texture_slot("frame", 0)?;
+    Ok(PostPass {
+      name: name.to_string(),
+      program,
+      enabled: false,
+      params: Vec::new(),
+      constants: Vec::new(),
+    })
+  }
+  pub fn param(mut self, name: &str, value: f32, range: RangeInclusive<f32>) -> Self {
+    self.params.push(Param {
+      name: name.to_string(),
+      value,
+      range,
+    });
+    self
+  }
+  pub fn constant(mut self, name: &str, value: &[f32]) -> Self {
+    self.constants.push((name.to_string(), value.to_vec()));
+    self
+  }
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// 以input为输入绘制到当前绑定的帧缓冲
+  fn draw(&self, input: glow::Texture) -> Result<(), Error> {
+    self.program.set_used();
+    // 每次绘制都上传，着色器热重载后无需恢复
+    for param in &self.params {
+      self.program.upload_float(&param.name, param.value)?;
+    }
+    for (name, value) in &self.constants {
+      self.program.upload(name, &value[..])?;
+    }
+    unsafe {
+      GL.active_texture(glow::TEXTURE0);
+      GL.bind_texture(glow::TEXTURE_2D, Some(input));
+      GL.draw_arrays(glow::TRIANGLES, 0, 3);
+    }
+    Ok(())
+  }
+}
+
+/// 按顺序执行的后处理链，在两个中间帧缓冲之间交替读写
+pub struct PostChain {
+  pub passes: Vec<PostPass>,
+  targets: [FrameBuffer; 2],
+  // 全屏三角形由顶点序号生成，只需绑定一个空的VAO
+  vao: VertexArray,
+  // 不论输入纹理自身的采样参数，均以边缘截取、线性过滤采样
+  sampler: Sampler,
+  // 最近一次apply的结果所在的帧缓冲
+  output: Cell<Option<usize>>,
+}
+impl PostChain {
+  /// 包含所有内置效果，默认均未开启
+  /// desc: 中间帧缓冲的格式，通常与输入画面的颜色附件一致
+  pub fn new(res: &Resources, desc: &FrameBufferDesc) -> Result<PostChain, Error> {
+    let mut passes = Vec::with_capacity(Effect::ALL.len());
+    for effect in Effect::ALL {
+      passes.push(effect.pass(res)?);
+    }
+    Ok(PostChain {
+      passes,
+      targets: Self::create_targets(desc)?,
+      vao: VertexArray::new(),
+      sampler: Sampler::new(SamplerDesc::screen()),
+      output: Cell::new(None),
+    })
+  }
+  fn create_targets(desc: &FrameBufferDesc) -> Result<[FrameBuffer; 2], Error> {
+    // 中间结果无需深度与多重采样
+    let desc = desc.clone().depth(Depth::None).samples(1);
+    Ok([desc.build()?, desc.build()?])
+  }
+  pub fn resize(&mut self, desc: &FrameBufferDesc) -> Result<(), Error> {
+    self.targets = Self::create_targets(desc)?;
+    self.output.set(None);
+    Ok(())
+  }
+
+  /// 依次执行开启的步骤，没有开启的步骤时返回None
+  pub fn apply(&self, input: glow::Texture) -> Result<Option<&FrameBuffer>, Error> {
+    let mut texture = input;
+    let mut output = None;
+    unsafe {
+      GL.disable(glow::DEPTH_TEST);
+      GL.disable(glow::BLEND);
+    }
+    self.vao.bind();
+    self.sampler.bind(0);
+    let result = self
+      .passes
+      .iter()
+      .filter(|pass| pass.enabled)
+      .try_for_each(|pass| {
+        let index = output.map_or(0, |i| 1 - i);
+        let target = &self.targets[index];
+        target.bind();
+        pass.draw(texture)?;
+        texture = target.texture();
+        output = Some(index);
+        Ok(())
+      });
+    Sampler::unbind(0);
+    self.vao.unbind();
+    unsafe {
+      GL.bind_framebuffer(glow::FRAMEBUFFER, None);
+      GL.enable(glow::BLEND);
+    }
+    self.output.set(output);
+    result.map(|()| self.output())
+  }
+  /// 最近一次apply的结果
+  pub fn output(&self) -> Option<&FrameBuffer> {
+    self.output.get().map(|i| &self.targets[i])
+  }
+
+  /// 开关、排序各步骤并调节参数
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    let mut swap = None;
+    let count = self.passes.len();
+    for (i, pass) in self.passes.iter_mut().enumerate() {
+      ui.horizontal(|ui| {
+        ui.checkbox(&mut pass.enabled, pass.name.as_str());
+        if ui.add_enabled(i > 0, egui::Button::new("↑")).clicked() {
+          swap = Some((i - 1, i));
+        }
+        if ui
+          .add_enabled(i + 1 < count, egui::Button::new("↓"))
+          .clicked()
+        {
+          swap = Some((i, i + 1));
+        }
+      });
+      if pass.enabled {
+        for param in &mut pass.params {
+          ui.add(egui::Slider::new(&mut param.value, param.range.clone()).text(&param.name));
+        }
+      }
+    }
+    if let Some((a, b)) = swap {
+      self.passes.swap(a, b);
+    }
+  }
+}
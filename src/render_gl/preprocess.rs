@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+
+use crate::render_gl::shader::Error;
+use crate::resources::Resources;
+
+/// 展开`#include`后的着色器源码
+pub struct Source {
+  pub code: String,
+  // 源串编号(#line 的第二个参数)对应的资源名，0为入口文件
+  pub files: Vec<String>,
+}
+
+/// 展开着色器中的 `#include "path"` 与 `#import "path"`
+/// 路径相对于当前文件所在目录；`#include`每次都展开，`#import`对同一文件只展开一次
+/// 展开处插入`#line`指令，使编译错误的行号指向原始文件
+pub fn preprocess(res: &Resources, name: &str) -> Result<Source, Error> {
+  let mut state = State {
+    res,
+    files: Vec::new(),
+    stack: Vec::new(),
+    imported: HashSet::new(),
+  };
+  let mut code = String::new();
+  state.expand(name, &mut code)?;
+  Ok(Source {
+    code,
+    files: state.files,
+  })
+}
+
+struct State<'a> {
+  res: &'a Resources,
+  files: Vec<String>,
+  // 当前的包含链，用于检测循环包含
+  stack: Vec<String>,
+  imported: HashSet<String>,
+}
+
+impl State<'_> {
+  fn expand(&mut self, name: &str, out: &mut String) -> Result<(), Error> {
+    if self.stack.iter().any(|n| n == name) {
+      let mut chain = self.stack.clone();
+      chain.push(name.to_string());
+      return Err(Error::IncludeCycle {
+        chain: chain.join(" -> "),
+      });
+    }
+    let source = self
+      .res
+      .load_string(name)
+      .map_err(|e| Error::ResourceLoad {
+        name: format!("Resources {:?} {}", self.res.get_root_path(), name),
+        inner: e,
+      })?;
+    let index = match self.files.iter().position(|n| n == name) {
+      Some(index) => index,
+      None => {
+        self.files.push(name.to_string());
+        self.files.len() - 1
+      }
+    };
+    let is_root = self.stack.is_empty();
+    self.stack.push(name.to_string());
+
+    // #version 必须位于首行，只保留入口文件中的
+    let mut need_line = !is_root;
+    // 块注释中的指令不展开
+    let mut in_comment = false;
+    for (i, line) in source.lines().enumerate() {
+      let visible = strip_comments(line, &mut in_comment);
+      let trimmed = visible.trim_start();
+      if need_line && !trimmed.starts_with("#version") {
+        out.push_str(&format!("#line {} {}\n", i + 1, index));
+        need_line = false;
+      }
+      if trimmed.starts_with("#version") {
+        if is_root {
+          out.push_str(line);
+        }
+        out.push('\n');
+        need_line = is_root;
+        continue;
+      }
+      let (directive, once) = if trimmed.starts_with("#include") {
+        ("#include", false)
+      } else if trimmed.starts_with("#import") {
+        ("#import", true)
+      } else {
+        out.push_str(line);
+        out.push('\n');
+        continue;
+      };
+      let target =
+        parse_path(&trimmed[directive.len()..]).ok_or_else(|| Error::InvalidDirective {
+          name: name.to_string(),
+          line: i + 1,
+        })?;
+      let target = resolve(name, target);
+      if !once || self.imported.insert(target.clone()) {
+        self.expand(&target, out)?;
+      }
+      // 回到当前文件的下一行
+      out.push_str(&format!("#line {} {}\n", i + 2, index));
+    }
+
+    self.stack.pop();
+    Ok(())
+  }
+}
+
+/// 去除一行中的注释，只保留注释之外的部分
+/// in_comment: 是否位于跨行的块注释中，处理完该行后更新
+fn strip_comments(line: &str, in_comment: &mut bool) -> String {
+  let mut out = String::with_capacity(line.len());
+  let mut rest = line;
+  loop {
+    if *in_comment {
+      match rest.find("*/") {
+        Some(end) => {
+          // 注释视为一个空格
+          out.push(' ');
+          rest = &rest[end + 2..];
+          *in_comment = false;
+        }
+        None => return out,
+      }
+    } else {
+      let block = rest.find("/*");
+      let line_comment = rest.find("//");
+      let block = match (block, line_comment) {
+        (Some(block), Some(line_comment)) if block < line_comment => block,
+        (Some(block), None) => block,
+        (_, Some(line_comment)) => {
+          out.push_str(&rest[..line_comment]);
+          return out;
+        }
+        (None, None) => {
+          out.push_str(rest);
+          return out;
+        }
+      };
+      out.push_str(&rest[..block]);
+      rest = &rest[block + 2..];
+      *in_comment = true;
+    }
+  }
+}
+
+// 解析 "path" 或 <path>
+fn parse_path(rest: &str) -> Option<&str> {
+  let rest = rest.trim();
+  let (open, close) = match rest.chars().next()? {
+    '"' => ('"', '"'),
+    '<' => ('<', '>'),
+    _ => return None,
+  };
+  let inner = rest.strip_prefix(open)?;
+  let end = inner.find(close)?;
+  Some(&inner[..end])
+}
+
+/// 将相对于`from`所在目录的路径解析为资源名
+fn resolve(from: &str, path: &str) -> String {
+  let mut parts: Vec<&str> = from.split('/').collect();
+  parts.pop();
+  for part in path.split('/') {
+    match part {
+      "" | "." => {}
+      ".." => {
+        parts.pop();
+      }
+      part => parts.push(part),
+    }
+  }
+  parts.join("/")
+}
+
+/// 将驱动信息日志中的源串编号替换为文件名
+/// 兼容 "0(12) : error"(NVIDIA) 与 "0:12(5): error"/"ERROR: 0:12:"(Mesa, AMD) 等格式
+pub fn remap_log(log: &str, files: &[String]) -> String {
+  log
+    .lines()
+    .map(|line| remap_line(line, files))
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn remap_line(line: &str, files: &[String]) -> String {
+  let bytes = line.as_bytes();
+  let mut start = 0;
+  while start < bytes.len() {
+    if bytes[start].is_ascii_digit() && (start == 0 || !bytes[start - 1].is_ascii_alphanumeric()) {
+      let mut end = start;
+      while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+      }
+      let followed_by_line = end + 1 < bytes.len()
+        && (bytes[end] == b'(' || bytes[end] == b':')
+        && bytes[end + 1].is_ascii_digit();
+      // 第一个位置即为源串编号，编号无效时保持原样
+      if followed_by_line {
+        return match line[start..end]
+          .parse::<usize>()
+          .ok()
+          .and_then(|index| files.get(index))
+        {
+          Some(file) => format!("{}{}{}", &line[..start], file, &line[end..]),
+          None => line.to_string(),
+        };
+      }
+      start = end;
+    } else {
+      start += 1;
+    }
+  }
+  line.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// 在临时目录中写入着色器文件，返回以其为根目录的Resources
+  fn resources(test: &str, files: &[(&str, &str)]) -> Resources {
+    let root = std::env::temp_dir().join("another-preprocess").join(test);
+    let _ = std::fs::remove_dir_all(&root);
+    for (name, source) in files {
+      let path = root.join(name);
+      std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+      std::fs::write(path, source).unwrap();
+    }
+    Resources::from_path(root)
+  }
+
+  #[test]
+  fn resolve_relative_paths() {
+    assert_eq!(
+      resolve("shaders/phong.frag", "common/a.glsl"),
+      "shaders/common/a.glsl"
+    );
+    assert_eq!(
+      resolve("shaders/common/a.glsl", "./b.glsl"),
+      "shaders/common/b.glsl"
+    );
+    assert_eq!(
+      resolve("shaders/common/a.glsl", "../c.glsl"),
+      "shaders/c.glsl"
+    );
+    assert_eq!(resolve("main.frag", "a.glsl"), "a.glsl");
+  }
+
+  #[test]
+  fn parse_quoted_and_angled_paths() {
+    assert_eq!(parse_path(r#" "common/a.glsl""#), Some("common/a.glsl"));
+    assert_eq!(
+      parse_path(" <common/a.glsl> // 注释"),
+      Some("common/a.glsl")
+    );
+    assert_eq!(parse_path(r#" "unterminated"#), None);
+    assert_eq!(parse_path(" common/a.glsl"), None);
+    assert_eq!(parse_path(""), None);
+  }
+
+  #[test]
+  fn expand_nested_includes() {
+    let res = resources(
+      "nested",
+      &[
+        (
+          "main.frag",
+          "#version 450 core\n#include \"a.glsl\"\nvoid main() {}\n",
+        ),
+        ("a.glsl", "float a;\n#include \"lib/b.glsl\"\n"),
+        ("lib/b.glsl", "float b;\n"),
+      ],
+    );
+    let source = preprocess(&res, "main.frag").unwrap();
+    assert_eq!(source.files, ["main.frag", "a.glsl", "lib/b.glsl"]);
+    assert_eq!(
+      source.code,
+      "#version 450 core\n\
+       #line 2 0\n\
+       #line 1 1\n\
+       float a;\n\
+       #line 1 2\n\
+       float b;\n\
+       #line 3 1\n\
+       #line 3 0\n\
+       void main() {}\n"
+    );
+  }
+
+  #[test]
+  fn import_expands_once() {
+    let res = resources(
+      "import",
+      &[
+        (
+          "main.frag",
+          "#import \"a.glsl\"\n#import \"a.glsl\"\n#include \"a.glsl\"\n",
+        ),
+        ("a.glsl", "float a;\n"),
+      ],
+    );
+    let source = preprocess(&res, "main.frag").unwrap();
+    assert_eq!(source.code.matches("float a;").count(), 2);
+  }
+
+  #[test]
+  fn detect_include_cycle() {
+    let res = resources(
+      "cycle",
+      &[
+        ("main.frag", "#include \"a.glsl\"\n"),
+        ("a.glsl", "#include \"b.glsl\"\n"),
+        ("b.glsl", "#include \"a.glsl\"\n"),
+      ],
+    );
+    match preprocess(&res, "main.frag") {
+      Err(Error::IncludeCycle { chain }) => {
+        assert_eq!(chain, "main.frag -> a.glsl -> b.glsl -> a.glsl")
+      }
+      _ => panic!("应检测到循环包含"),
+    }
+  }
+
+  #[test]
+  fn skip_commented_directives() {
+    let res = resources(
+      "comment",
+      &[
+        (
+          "main.frag",
+          "// #include \"missing.glsl\"\n\
+           /*\n\
+           #include \"missing.glsl\"\n\
+           */ #include \"a.glsl\"\n\
+           /* #import \"missing.glsl\" */\n",
+        ),
+        ("a.glsl", "float a;\n"),
+      ],
+    );
+    let source = preprocess(&res, "main.frag").unwrap();
+    assert_eq!(source.files, ["main.frag", "a.glsl"]);
+    assert!(source.code.contains("float a;"));
+  }
+
+  #[test]
+  fn strip_comments_across_lines() {
+    let mut in_comment = false;
+    assert_eq!(
+      strip_comments("a /* b */ c // d", &mut in_comment),
+      "a   c "
+    );
+    assert!(!in_comment);
+    assert_eq!(strip_comments("a /* b", &mut in_comment), "a ");
+    assert!(in_comment);
+    assert_eq!(strip_comments("#include \"x\"", &mut in_comment), "");
+    assert_eq!(strip_comments("*/ b", &mut in_comment), "  b");
+    assert!(!in_comment);
+  }
+
+  #[test]
+  fn remap_driver_logs() {
+    let files = vec!["main.frag".to_string(), "common/a.glsl".to_string()];
+    // NVIDIA
+    assert_eq!(
+      remap_line("1(12) : error C0000: syntax error", &files),
+      "common/a.glsl(12) : error C0000: syntax error"
+    );
+    // Mesa
+    assert_eq!(
+      remap_line("0:3(5): error: `x' undeclared", &files),
+      "main.frag:3(5): error: `x' undeclared"
+    );
+    // AMD
+    assert_eq!(
+      remap_line("ERROR: 1:7: 'y' : undeclared identifier", &files),
+      "ERROR: common/a.glsl:7: 'y' : undeclared identifier"
+    );
+    // 超出范围的编号与不含位置的行保持不变
+    assert_eq!(remap_line("5:1(1): error", &files), "5:1(1): error");
+    assert_eq!(remap_line("link error", &files), "link error");
+    assert_eq!(
+      remap_log("0:1(1): a\n1:2(2): b", &files),
+      "main.frag:1(1): a\ncommon/a.glsl:2(2): b"
+    );
+  }
+}
@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::SystemTime;
+
+use dashmap::DashMap;
+use glow::HasContext;
+use once_cell::sync::Lazy;
+
+use crate::render_gl::shader::{self, Error, Shader};
+use crate::resources::Resources;
+use crate::GL;
+
+/// 被监视的着色程序，新程序链接成功后暂存于pending，由Program在set_used时换入
+struct Watched {
+  name: String,
+  res: Resources,
+  // 各着色器阶段的资源名
+  stages: Vec<String>,
+  // 监视的文件(含被包含的文件)及其修改时间
+  files: Vec<(PathBuf, Option<SystemTime>)>,
+  pending: Option<glow::Program>,
+  error: Option<String>,
+}
+
+static WATCHED: Lazy<DashMap<u32, Watched>> = Lazy::new(DashMap::new);
+static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+  fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn watch_files(res: &Resources, shaders: &[Shader]) -> Vec<(PathBuf, Option<SystemTime>)> {
+  let mut files: Vec<(PathBuf, Option<SystemTime>)> = Vec::new();
+  for name in shaders.iter().flat_map(|shader| shader.files()) {
+    let path = res.resolve_path(name);
+    if !files.iter().any(|(p, _)| *p == path) {
+      let modified = modified_time(&path);
+      files.push((path, modified));
+    }
+  }
+  files
+}
+
+/// 登记着色程序的各阶段与其依赖的文件，返回监视id
+pub(super) fn register(
+  name: &str,
+  res: &Resources,
+  stages: Vec<String>,
+  shaders: &[Shader],
+) -> u32 {
+  let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+  WATCHED.insert(
+    id,
+    Watched {
+      name: name.to_string(),
+      res: res.clone(),
+      stages,
+      files: watch_files(res, shaders),
+      pending: None,
+      error: None,
+    },
+  );
+  id
+}
+
+pub(super) fn unregister(id: u32) {
+  if let Some((_, watched)) = WATCHED.remove(&id) {
+    if let Some(pending) = watched.pending {
+      unsafe { GL.delete_program(pending) };
+    }
+  }
+}
+
+/// 取出已链接成功、等待换入的新程序
+pub(super) fn take_pending(id: u32) -> Option<glow::Program> {
+  WATCHED.get_mut(&id)?.pending.take()
+}
+
+fn rebuild(watched: &mut Watched) -> Result<glow::Program, Error> {
+  let shaders = watched
+    .stages
+    .iter()
+    .map(|name| Shader::from_res(&watched.res, name))
+    .collect::<Result<Vec<Shader>, Error>>()?;
+  // 包含关系可能已改变
+  watched.files = watch_files(&watched.res, &shaders);
+  shader::link(&shaders)
+}
+
+/// 检查所有被监视的源文件，有改动时重新编译并链接
+/// 失败时保留旧程序，错误信息可由`errors`获取
+pub fn poll() {
+  for mut entry in WATCHED.iter_mut() {
+    let watched = entry.value_mut();
+    let mut changed = false;
+    for (path, modified) in watched.files.iter_mut() {
+      let now = modified_time(path);
+      if now != *modified {
+        *modified = now;
+        changed = true;
+      }
+    }
+    if !changed {
+      continue;
+    }
+    match rebuild(watched) {
+      Ok(program) => {
+        if let Some(old) = watched.pending.replace(program) {
+          unsafe { GL.delete_program(old) };
+        }
+        watched.error = None;
+        println!("着色程序已重新加载 {}", watched.name);
+      }
+      Err(e) => watched.error = Some(e.to_string()),
+    }
+  }
+}
+
+/// 重新加载失败的着色程序及其错误信息
+pub fn errors() -> Vec<(String, String)> {
+  let mut errors: Vec<(String, String)> = WATCHED
+    .iter()
+    .filter_map(|entry| {
+      let watched = entry.value();
+      watched
+        .error
+        .as_ref()
+        .map(|error| (watched.name.clone(), error.clone()))
+    })
+    .collect();
+  errors.sort();
+  errors
+}
@@ -0,0 +1,235 @@
+use glow::HasContext;
+use once_cell::sync::OnceCell;
+
+use crate::GL;
+
+// 各向异性过滤的驱动上限，None为不支持该扩展
+static MAX_ANISOTROPY: OnceCell<Option<f32>> = OnceCell::new();
+
+/// 查询并缓存各向异性过滤的支持情况，需在GL上下文创建后调用
+pub fn init() {
+  max_anisotropy();
+}
+
+/// 各向异性过滤在4.6进入核心，此前为EXT/ARB扩展
+fn max_anisotropy() -> Option<f32> {
+  *MAX_ANISOTROPY.get_or_init(|| {
+    let extensions = GL.supported_extensions();
+    let version = GL.version();
+    let supported = (!version.is_embedded && (version.major, version.minor) >= (4, 6))
+      || extensions.contains("GL_EXT_texture_filter_anisotropic")
+      || extensions.contains("GL_ARB_texture_filter_anisotropic");
+    supported.then(|| unsafe { GL.get_parameter_f32(glow::MAX_TEXTURE_MAX_ANISOTROPY) }.max(1.0))
+  })
+}
+
+/// 纹理坐标超出[0, 1]时的环绕方式
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Wrap {
+  Repeat,
+  MirroredRepeat,
+  ClampToEdge,
+  // 使用border_color
+  ClampToBorder,
+}
+impl Wrap {
+  fn gl(self) -> i32 {
+    (match self {
+      Wrap::Repeat => glow::REPEAT,
+      Wrap::MirroredRepeat => glow::MIRRORED_REPEAT,
+      Wrap::ClampToEdge => glow::CLAMP_TO_EDGE,
+      Wrap::ClampToBorder => glow::CLAMP_TO_BORDER,
+    }) as i32
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+  Nearest,
+  Linear,
+}
+
+/// 8位RGB(A)图片上传时的颜色空间
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+  // 基础色、自发光等颜色纹理，采样时由GL转换到线性空间
+  Srgb,
+  // 法线、金属度/粗糙度等数据纹理，按原值采样
+  Linear,
+}
+
+/// 纹理采样参数，可用于纹理本身，也可用于创建独立的`Sampler`
+/// mipmap为None时不使用(也不生成)多级渐远纹理
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SamplerDesc {
+  pub wrap_s: Wrap,
+  pub wrap_t: Wrap,
+  pub wrap_r: Wrap,
+  pub border_color: [f32; 4],
+  pub min_filter: Filter,
+  pub mag_filter: Filter,
+  pub mipmap: Option<Filter>,
+  // 各向异性过滤的最大采样数，1.0为关闭，超出驱动上限时取上限，不支持时忽略
+  pub anisotropy: f32,
+  // 仅在由图片创建纹理时生效，对独立的`Sampler`无效
+  pub color_space: ColorSpace,
+}
+impl Default for SamplerDesc {
+  fn default() -> Self {
+    SamplerDesc {
+      wrap_s: Wrap::Repeat,
+      wrap_t: Wrap::Repeat,
+      wrap_r: Wrap::Repeat,
+      border_color: [0.0, 0.0, 0.0, 0.0],
+      min_filter: Filter::Linear,
+      mag_filter: Filter::Linear,
+      mipmap: Some(Filter::Linear),
+      anisotropy: 1.0,
+      color_space: ColorSpace::Linear,
+    }
+  }
+}
+
+impl SamplerDesc {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  /// 像素风纹理：最近邻过滤，不使用mipmap
+  pub fn pixel_art() -> Self {
+    Self::new().filter(Filter::Nearest).mipmap(None)
+  }
+  /// 帧缓冲等屏幕空间纹理：边缘截取，线性过滤，不使用mipmap
+  pub fn screen() -> Self {
+    Self::new().wrap(Wrap::ClampToEdge).mipmap(None)
+  }
+  /// 颜色纹理：按sRGB解码，其余为默认值
+  pub fn srgb() -> Self {
+    Self::new().color_space(ColorSpace::Srgb)
+  }
+
+  /// 同时设置三个方向的环绕方式
+  pub fn wrap(self, wrap: Wrap) -> Self {
+    SamplerDesc {
+      wrap_s: wrap,
+      wrap_t: wrap,
+      wrap_r: wrap,
+      ..self
+    }
+  }
+  pub fn border_color(self, border_color: [f32; 4]) -> Self {
+    SamplerDesc {
+      border_color,
+      ..self
+    }
+  }
+  /// 同时设置缩小与放大过滤
+  pub fn filter(self, filter: Filter) -> Self {
+    SamplerDesc {
+      min_filter: filter,
+      mag_filter: filter,
+      ..self
+    }
+  }
+  pub fn min_filter(self, min_filter: Filter) -> Self {
+    SamplerDesc { min_filter, ..self }
+  }
+  pub fn mag_filter(self, mag_filter: Filter) -> Self {
+    SamplerDesc { mag_filter, ..self }
+  }
+  pub fn mipmap(self, mipmap: Option<Filter>) -> Self {
+    SamplerDesc { mipmap, ..self }
+  }
+  pub fn anisotropy(self, anisotropy: f32) -> Self {
+    SamplerDesc { anisotropy, ..self }
+  }
+  pub fn color_space(self, color_space: ColorSpace) -> Self {
+    SamplerDesc {
+      color_space,
+      ..self
+    }
+  }
+
+  fn gl_min_filter(&self) -> i32 {
+    (match (self.min_filter, self.mipmap) {
+      (Filter::Nearest, None) => glow::NEAREST,
+      (Filter::Linear, None) => glow::LINEAR,
+      (Filter::Nearest, Some(Filter::Nearest)) => glow::NEAREST_MIPMAP_NEAREST,
+      (Filter::Nearest, Some(Filter::Linear)) => glow::NEAREST_MIPMAP_LINEAR,
+      (Filter::Linear, Some(Filter::Nearest)) => glow::LINEAR_MIPMAP_NEAREST,
+      (Filter::Linear, Some(Filter::Linear)) => glow::LINEAR_MIPMAP_LINEAR,
+    }) as i32
+  }
+  fn gl_mag_filter(&self) -> i32 {
+    (match self.mag_filter {
+      Filter::Nearest => glow::NEAREST,
+      Filter::Linear => glow::LINEAR,
+    }) as i32
+  }
+
+  fn apply(
+    &self,
+    set_i32: impl Fn(u32, i32),
+    set_f32: impl Fn(u32, f32),
+    set_f32v: impl Fn(u32, &[f32]),
+  ) {
+    set_i32(glow::TEXTURE_WRAP_S, self.wrap_s.gl());
+    set_i32(glow::TEXTURE_WRAP_T, self.wrap_t.gl());
+    set_i32(glow::TEXTURE_WRAP_R, self.wrap_r.gl());
+    set_f32v(glow::TEXTURE_BORDER_COLOR, &self.border_color);
+    set_i32(glow::TEXTURE_MIN_FILTER, self.gl_min_filter());
+    set_i32(glow::TEXTURE_MAG_FILTER, self.gl_mag_filter());
+    if let Some(max) = max_anisotropy() {
+      set_f32(
+        glow::TEXTURE_MAX_ANISOTROPY,
+        self.anisotropy.clamp(1.0, max),
+      );
+    }
+  }
+
+  /// 设置当前绑定到target的纹理的采样参数
+  pub fn apply_to_texture(&self, target: u32) {
+    self.apply(
+      |name, value| unsafe { GL.tex_parameter_i32(target, name, value) },
+      |name, value| unsafe { GL.tex_parameter_f32(target, name, value) },
+      |name, value| unsafe { GL.tex_parameter_f32_slice(target, name, value) },
+    );
+  }
+}
+
+/// 独立的采样器对象，绑定到纹理单元后覆盖该单元上纹理自身的采样参数
+pub struct Sampler {
+  inner: glow::Sampler,
+  desc: SamplerDesc,
+}
+impl Sampler {
+  pub fn new(desc: SamplerDesc) -> Sampler {
+    let inner = unsafe { GL.create_sampler().unwrap() };
+    desc.apply(
+      |name, value| unsafe { GL.sampler_parameter_i32(inner, name, value) },
+      |name, value| unsafe { GL.sampler_parameter_f32(inner, name, value) },
+      |name, value| unsafe { GL.sampler_parameter_f32_slice(inner, name, value) },
+    );
+    Sampler { inner, desc }
+  }
+  pub fn desc(&self) -> &SamplerDesc {
+    &self.desc
+  }
+  /// unit: 纹理单元序号，如0对应TEXTURE0
+  pub fn bind(&self, unit: u32) {
+    unsafe {
+      GL.bind_sampler(unit, Some(self.inner));
+    }
+  }
+  pub fn unbind(unit: u32) {
+    unsafe {
+      GL.bind_sampler(unit, None);
+    }
+  }
+}
+impl Drop for Sampler {
+  fn drop(&mut self) {
+    unsafe {
+      GL.delete_sampler(self.inner);
+    }
+  }
+}
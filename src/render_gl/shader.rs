@@ -1,8 +1,13 @@
+use crate::render_gl::preprocess::{self, remap_log};
+use crate::render_gl::uniform::{Uniform, UniformValue};
+use crate::render_gl::{buffer, reload};
 use crate::resources;
 use crate::resources::Resources;
 use crate::GL;
 use glow::HasContext;
 use na::{Matrix3, Matrix4, Point3, Vector2, Vector3, Vector4};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -19,101 +24,284 @@ pub enum Error {
   CompileError { message: String },
   #[error("着色程序链接失败 : {}", message)]
   LinkError { message: String },
+  #[error("找不到着色器 {} 的任何阶段", name)]
+  NoShaderStage { name: String },
+  #[error("着色器循环包含: {}", chain)]
+  IncludeCycle { chain: String },
+  #[error("无效的预处理指令 {} 第{}行", name, line)]
+  InvalidDirective { name: String, line: usize },
+  #[error("着色程序 {} 中不存在uniform {}", program, name)]
+  UnknownUniform { program: String, name: String },
+  #[error("着色程序 {} 中不存在uniform块 {}", program, block)]
+  UnknownUniformBlock { program: String, block: String },
+  #[error("uniform块binding点分配失败")]
+  UniformBinding(#[from] buffer::Error),
 }
 
 pub struct Program {
-  inner: glow::Program,
+  name: String,
+  // 热重载时会被替换
+  inner: Cell<glow::Program>,
+  // 源文件的监视id，由from_shaders创建的程序不参与热重载
+  watch_id: Option<u32>,
+  // uniform名 -> 位置，每次链接后重建
+  locations: RefCell<HashMap<String, glow::UniformLocation>>,
+  // 链接次数，用于使旧的Uniform句柄失效
+  generation: Cell<u32>,
+  strict: Cell<bool>,
+  // 重载后需重新设置的纹理单元与uniform块
+  texture_slots: RefCell<Vec<(String, i32)>>,
+  uniform_blocks: RefCell<Vec<String>>,
 }
 impl Program {
+  /// 链接 name.vert/.tesc/.tese/.geom/.frag 中存在的各阶段
   pub fn from_res(res: &Resources, name: &str) -> Result<Program, Error> {
-    const POSSIBLE_EXT: [&str; 2] = [".vert", ".frag"];
-    let shaders = POSSIBLE_EXT
+    const POSSIBLE_EXT: [&str; 5] = [".vert", ".tesc", ".tese", ".geom", ".frag"];
+    let stages: Vec<String> = POSSIBLE_EXT
       .iter()
-      .map(|file_extension| Shader::from_res(res, &format!("{}{}", name, file_extension)))
+      .map(|file_extension| format!("{}{}", name, file_extension))
+      .filter(|stage| res.resolve_path(stage).is_file())
+      .collect();
+    if stages.is_empty() {
+      return Err(Error::NoShaderStage {
+        name: name.to_string(),
+      });
+    }
+    Program::from_stages(res, name, stages)
+  }
+
+  /// stages: 各着色器阶段的资源名，如 "shaders/particle.comp"
+  pub(super) fn from_stages(
+    res: &Resources,
+    name: &str,
+    stages: Vec<String>,
+  ) -> Result<Program, Error> {
+    let shaders = stages
+      .iter()
+      .map(|stage| Shader::from_res(res, stage))
       .collect::<Result<Vec<Shader>, Error>>()?;
-    Ok(Program::from_shaders(&shaders[..])?)
+    let mut program = Program::from_shaders(&shaders[..])?;
+    program.name = name.to_string();
+    program.watch_id = Some(reload::register(name, res, stages, &shaders));
+    Ok(program)
   }
 
-  pub fn upload_texture_slot(&self, name: &str, slot: i32) -> Option<()> {
-    self.set_used();
-    unsafe {
-      let location = GL.get_uniform_location(self.inner, name)?;
-      GL.uniform_1_i32(Some(&location), slot);
-      Some(())
-    }
+  fn inner(&self) -> glow::Program {
+    self.inner.get()
   }
-  pub fn upload_mat4(&self, name: &str, mat4: &Matrix4<f32>) -> Option<()> {
-    self.set_used();
+
+  /// 开启后，上传不存在(或被优化掉)的uniform时返回`Error::UnknownUniform`，
+  /// 绑定不存在的uniform块时返回`Error::UnknownUniformBlock`，关闭时忽略
+  pub fn set_strict(&self, strict: bool) {
+    self.strict.set(strict);
+  }
+
+  /// 链接后查询所有活动uniform的位置，数组会同时登记 "name" 与 "name[i]"
+  fn build_locations(&self) {
+    let program = self.inner();
+    let mut locations = self.locations.borrow_mut();
+    locations.clear();
     unsafe {
-      let location = GL.get_uniform_location(self.inner, name)?;
-      GL.uniform_matrix_4_f32_slice(Some(&location), false, mat4.as_slice());
-      Some(())
+      for index in 0..GL.get_active_uniforms(program) {
+        let uniform = match GL.get_active_uniform(program, index) {
+          Some(uniform) => uniform,
+          None => continue,
+        };
+        let base = uniform.name.strip_suffix("[0]").unwrap_or(&uniform.name);
+        let mut names = vec![uniform.name.clone()];
+        if base.len() != uniform.name.len() {
+          names.push(base.to_string());
+          names.extend((1..uniform.size).map(|i| format!("{}[{}]", base, i)));
+        }
+        for name in names {
+          // uniform块中的成员没有位置
+          if let Some(location) = GL.get_uniform_location(program, &name) {
+            locations.insert(name, location);
+          }
+        }
+      }
     }
   }
-  pub fn upload_mat3(&self, name: &str, mat3: &Matrix3<f32>) -> Option<()> {
-    self.set_used();
-    unsafe {
-      let location = GL.get_uniform_location(self.inner, name)?;
-      GL.uniform_matrix_3_f32_slice(Some(&location), false, mat3.as_slice());
-      Some(())
+
+  pub fn location(&self, name: &str) -> Option<glow::UniformLocation> {
+    self.locations.borrow().get(name).copied()
+  }
+
+  /// 获取类型化的uniform句柄，名称不存在时返回`Error::UnknownUniform`
+  pub fn uniform<T: UniformValue + ?Sized>(&self, name: &str) -> Result<Uniform<T>, Error> {
+    self.swap_reloaded();
+    let location = self.location(name).ok_or_else(|| self.unknown(name))?;
+    Ok(Uniform::new(name, location, self.generation.get()))
+  }
+
+  /// 通过句柄上传，着色程序重新链接后会重新解析位置
+  /// 与`upload`相同，需先`set_used`，不存在时的处理取决于严格模式
+  pub fn set<T: UniformValue + ?Sized>(
+    &self,
+    uniform: &Uniform<T>,
+    value: &T,
+  ) -> Result<(), Error> {
+    if uniform.generation.get() != self.generation.get() {
+      uniform.location.set(self.location(&uniform.name));
+      uniform.generation.set(self.generation.get());
+    }
+    match uniform.location.get() {
+      Some(location) => {
+        value.upload(&location);
+        Ok(())
+      }
+      None => self.missing(&uniform.name),
     }
   }
-  pub fn upload_vec2(&self, name: &str, vec2: &Vector2<f32>) -> Option<()> {
-    self.set_used();
-    unsafe {
-      let location = GL.get_uniform_location(self.inner, name)?;
-      GL.uniform_2_f32(Some(&location), vec2.x, vec2.y);
-      Some(())
+
+  /// 按名称上传，名称不存在时总是返回`Error::UnknownUniform`
+  pub fn try_upload<T: UniformValue + ?Sized>(&self, name: &str, value: &T) -> Result<(), Error> {
+    let location = self.location(name).ok_or_else(|| self.unknown(name))?;
+    value.upload(&location);
+    Ok(())
+  }
+
+  /// 上传到当前使用的着色程序，每次绘制前只需`set_used`一次
+  /// 名称不存在时，严格模式下返回`Error::UnknownUniform`，否则忽略
+  pub fn upload<T: UniformValue + ?Sized>(&self, name: &str, value: &T) -> Result<(), Error> {
+    match self.location(name) {
+      Some(location) => {
+        value.upload(&location);
+        Ok(())
+      }
+      None => self.missing(name),
     }
   }
 
-  pub fn upload_vec3(&self, name: &str, vec3: &Vector3<f32>) -> Option<()> {
-    self.set_used();
-    unsafe {
-      let location = GL.get_uniform_location(self.inner, name)?;
-      GL.uniform_3_f32(Some(&location), vec3.x, vec3.y, vec3.z);
-      Some(())
+  fn missing(&self, name: &str) -> Result<(), Error> {
+    if self.strict.get() {
+      Err(self.unknown(name))
+    } else {
+      Ok(())
     }
   }
-  pub fn upload_point3(&self, name: &str, point3: &Point3<f32>) -> Option<()> {
-    self.upload_vec3(name, &point3.coords)
+
+  fn unknown(&self, name: &str) -> Error {
+    Error::UnknownUniform {
+      program: self.name.clone(),
+      name: name.to_string(),
+    }
   }
-  pub fn upload_vec4(&self, name: &str, vec4: &Vector4<f32>) -> Option<()> {
+
+  /// 设置采样器的纹理单元，热重载后自动恢复；会使用该着色程序
+  pub fn upload_texture_slot(&self, name: &str, slot: i32) -> Result<(), Error> {
+    let mut slots = self.texture_slots.borrow_mut();
+    match slots.iter_mut().find(|(slot_name, _)| slot_name == name) {
+      Some(recorded) => recorded.1 = slot,
+      None => slots.push((name.to_string(), slot)),
+    }
+    drop(slots);
     self.set_used();
+    self.upload(name, &slot)
+  }
+  pub fn upload_int(&self, name: &str, value: i32) -> Result<(), Error> {
+    self.upload(name, &value)
+  }
+  pub fn upload_uint(&self, name: &str, value: u32) -> Result<(), Error> {
+    self.upload(name, &value)
+  }
+  pub fn upload_bool(&self, name: &str, value: bool) -> Result<(), Error> {
+    self.upload(name, &value)
+  }
+  pub fn upload_float(&self, name: &str, value: f32) -> Result<(), Error> {
+    self.upload(name, &value)
+  }
+  pub fn upload_mat4(&self, name: &str, mat4: &Matrix4<f32>) -> Result<(), Error> {
+    self.upload(name, mat4)
+  }
+  pub fn upload_mat3(&self, name: &str, mat3: &Matrix3<f32>) -> Result<(), Error> {
+    self.upload(name, mat3)
+  }
+  pub fn upload_vec2(&self, name: &str, vec2: &Vector2<f32>) -> Result<(), Error> {
+    self.upload(name, vec2)
+  }
+
+  pub fn upload_vec3(&self, name: &str, vec3: &Vector3<f32>) -> Result<(), Error> {
+    self.upload(name, vec3)
+  }
+  pub fn upload_point3(&self, name: &str, point3: &Point3<f32>) -> Result<(), Error> {
+    self.upload(name, point3)
+  }
+  pub fn upload_vec4(&self, name: &str, vec4: &Vector4<f32>) -> Result<(), Error> {
+    self.upload(name, vec4)
+  }
+
+  /// 将uniform块绑定到按块名分配的binding点，见`buffer::uniform_binding`
+  pub fn bind_uniform_block(&self, block: &str) -> Result<(), Error> {
+    let mut blocks = self.uniform_blocks.borrow_mut();
+    if !blocks.iter().any(|name| name == block) {
+      blocks.push(block.to_string());
+    }
+    drop(blocks);
+    self.apply_uniform_block(block)
+  }
+  fn apply_uniform_block(&self, block: &str) -> Result<(), Error> {
+    let index = match unsafe { GL.get_uniform_block_index(self.inner(), block) } {
+      Some(index) => index,
+      None if self.strict.get() => {
+        return Err(Error::UnknownUniformBlock {
+          program: self.name.clone(),
+          block: block.to_string(),
+        })
+      }
+      None => return Ok(()),
+    };
+    let binding = buffer::uniform_binding(block)?;
     unsafe {
-      let location = GL.get_uniform_location(self.inner, name)?;
-      GL.uniform_4_f32(Some(&location), vec4.x, vec4.y, vec4.z, vec4.w);
-      Some(())
+      GL.uniform_block_binding(self.inner(), index, binding);
     }
+    Ok(())
   }
 
   pub fn from_shaders(shaders: &[Shader]) -> Result<Program, Error> {
-    let program = unsafe { GL.create_program().unwrap() };
-    for shader in shaders {
-      unsafe { GL.attach_shader(program, shader.inner) };
+    let program = Program {
+      name: String::from("<from_shaders>"),
+      inner: Cell::new(link(shaders)?),
+      watch_id: None,
+      locations: RefCell::new(HashMap::new()),
+      generation: Cell::new(0),
+      strict: Cell::new(false),
+      texture_slots: RefCell::new(Vec::new()),
+      uniform_blocks: RefCell::new(Vec::new()),
+    };
+    program.build_locations();
+    Ok(program)
+  }
+  /// 使用该着色程序，并在此时换入热重载后的新程序
+  pub fn set_used(&self) {
+    self.swap_reloaded();
+    unsafe {
+      GL.use_program(Some(self.inner()));
     }
+  }
+  /// 换入热重载后的新程序，并恢复纹理单元与uniform块的绑定
+  fn swap_reloaded(&self) {
+    let new = match self.watch_id.and_then(reload::take_pending) {
+      Some(new) => new,
+      None => return,
+    };
+    let old = self.inner.replace(new);
     unsafe {
-      GL.link_program(program);
+      GL.delete_program(old);
+      GL.use_program(Some(new));
     }
-    for shader in shaders {
-      unsafe {
-        GL.detach_shader(program, shader.inner);
+    self.build_locations();
+    self.generation.set(self.generation.get() + 1);
+    for (name, slot) in self.texture_slots.borrow().iter() {
+      if let Some(location) = self.location(name) {
+        slot.upload(&location);
       }
     }
-
-    unsafe {
-      if !GL.get_program_link_status(program) {
-        let info = GL.get_program_info_log(program);
-        return Err(Error::LinkError { message: info });
+    for block in self.uniform_blocks.borrow().iter() {
+      if let Err(e) = self.apply_uniform_block(block) {
+        println!("{:?}", e);
       }
     }
-
-    Ok(Program { inner: program })
-  }
-  pub fn set_used(&self) {
-    unsafe {
-      GL.use_program(Some(self.inner));
-    }
   }
   pub fn detach(&self) {
     unsafe {
@@ -124,42 +312,85 @@ impl Program {
 
 impl Drop for Program {
   fn drop(&mut self) {
+    if let Some(id) = self.watch_id {
+      reload::unregister(id);
+    }
     unsafe {
-      GL.delete_program(self.inner);
+      GL.delete_program(self.inner());
     }
   }
 }
 
+pub(super) fn link(shaders: &[Shader]) -> Result<glow::Program, Error> {
+  let program = unsafe { GL.create_program().unwrap() };
+  for shader in shaders {
+    unsafe { GL.attach_shader(program, shader.inner) };
+  }
+  unsafe {
+    GL.link_program(program);
+  }
+  for shader in shaders {
+    unsafe {
+      GL.detach_shader(program, shader.inner);
+    }
+  }
+
+  unsafe {
+    if !GL.get_program_link_status(program) {
+      let info = GL.get_program_info_log(program);
+      GL.delete_program(program);
+      return Err(Error::LinkError { message: info });
+    }
+  }
+  Ok(program)
+}
+
+/// 根据扩展名判断着色器类型
+fn shader_kind(name: &str) -> Result<u32, Error> {
+  const POSSIBLE_EXT: [(&str, u32); 6] = [
+    (".vert", glow::VERTEX_SHADER),
+    (".tesc", glow::TESS_CONTROL_SHADER),
+    (".tese", glow::TESS_EVALUATION_SHADER),
+    (".geom", glow::GEOMETRY_SHADER),
+    (".frag", glow::FRAGMENT_SHADER),
+    (".comp", glow::COMPUTE_SHADER),
+  ];
+
+  POSSIBLE_EXT
+    .iter()
+    .find(|&&(file_extension, _)| name.ends_with(file_extension))
+    .map(|&(_, kind)| kind)
+    .ok_or_else(|| Error::CanNotDetermineShaderTypeForResource {
+      name: format!("无法判断给定resource的渲染器类型 {}", name),
+    })
+}
+
 pub struct Shader {
   inner: glow::Shader,
+  // 参与编译的所有文件(含被包含的文件)
+  files: Vec<String>,
 }
 
 impl Shader {
   pub fn from_res(res: &Resources, name: &str) -> Result<Shader, Error> {
-    const POSSIBLE_EXT: [(&str, u32); 2] = [
-      (".vert", glow::VERTEX_SHADER),
-      (".frag", glow::FRAGMENT_SHADER),
-    ];
+    let shader_kind = shader_kind(name)?;
 
-    let shader_kind = POSSIBLE_EXT
-      .iter()
-      .find(|&&(file_extension, _)| name.ends_with(file_extension))
-      .map(|&(_, kind)| kind)
-      .ok_or_else(|| Error::CanNotDetermineShaderTypeForResource {
-        name: format!("无法判断给定resource的渲染器类型 {}", name),
-      })?;
-
-    let source = res.load_string(name).map_err(|e| Error::ResourceLoad {
-      name: format!("Resources {:?} {}", &res.get_root_path(), name),
-      inner: e,
-    })?;
-
-    Shader::from_source(&source, shader_kind, name)
+    let source = preprocess::preprocess(res, name)?;
+    let inner = shader_from_source(&source.code, shader_kind, name, &source.files)?;
+    Ok(Shader {
+      inner,
+      files: source.files,
+    })
   }
 
   pub fn from_source(source: &str, kind: u32, name: &str) -> Result<Shader, Error> {
-    let inner = shader_from_source(source, kind, name)?;
-    Ok(Shader { inner })
+    let files = vec![name.to_string()];
+    let inner = shader_from_source(source, kind, name, &files)?;
+    Ok(Shader { inner, files })
+  }
+
+  pub fn files(&self) -> &[String] {
+    &self.files
   }
 
   pub fn from_vert_source(source: &str, name: &str) -> Result<Shader, Error> {
@@ -177,7 +408,12 @@ impl Drop for Shader {
   }
 }
 
-fn shader_from_source(source: &str, shader_type: u32, name: &str) -> Result<glow::Shader, Error> {
+fn shader_from_source(
+  source: &str,
+  shader_type: u32,
+  name: &str,
+  files: &[String],
+) -> Result<glow::Shader, Error> {
   let shader = unsafe { GL.create_shader(shader_type).unwrap() };
 
   unsafe {
@@ -189,8 +425,9 @@ fn shader_from_source(source: &str, shader_type: u32, name: &str) -> Result<glow
 
   if !success {
     let info = unsafe { GL.get_shader_info_log(shader) };
+    unsafe { GL.delete_shader(shader) };
     return Err(Error::CompileError {
-      message: format!("{}{}", name, info),
+      message: format!("{}\n{}", name, remap_log(&info, files)),
     });
   }
   Ok(shader)
@@ -0,0 +1,90 @@
+use glow::HasContext;
+
+use crate::geom::camera::Camera;
+use crate::render_gl::cubemap::Cubemap;
+use crate::render_gl::data::*;
+use crate::render_gl::mesh::Mesh;
+use crate::resources::Resources;
+use crate::{render_gl, GL};
+
+#[derive(VertexAttribPointers, Copy, Clone, Debug)]
+#[repr(C, packed)]
+struct Vertex {
+  #[location = 0]
+  pos: f32_f32_f32,
+}
+
+/// 以摄像机为中心的单位立方体，顶点坐标即立方体贴图的采样方向
+pub(super) fn cube_mesh() -> Mesh {
+  let vertices: Vec<Vertex> = [
+    (-1.0, -1.0, -1.0),
+    (1.0, -1.0, -1.0),
+    (1.0, 1.0, -1.0),
+    (-1.0, 1.0, -1.0),
+    (-1.0, -1.0, 1.0),
+    (1.0, -1.0, 1.0),
+    (1.0, 1.0, 1.0),
+    (-1.0, 1.0, 1.0),
+  ]
+  .iter()
+  .map(|pos| Vertex { pos: (*pos).into() })
+  .collect();
+  let indices: Vec<u32> = vec![
+    0, 1, 2, 0, 2, 3, // -Z
+    4, 6, 5, 4, 7, 6, // +Z
+    0, 3, 7, 0, 7, 4, // -X
+    1, 5, 6, 1, 6, 2, // +X
+    3, 2, 6, 3, 6, 7, // +Y
+    0, 4, 5, 0, 5, 1, // -Y
+  ];
+  Mesh::new(&vertices, &indices, Vertex::vertex_attrib_pointers)
+}
+
+/// 天空盒，需在不透明物体之后绘制
+pub struct Skybox {
+  program: render_gl::Program,
+  mesh: Mesh,
+  cubemap: Cubemap,
+}
+impl Skybox {
+  pub fn new(res: &Resources, cubemap: Cubemap) -> Result<Skybox, anyhow::Error> {
+    let program = render_gl::Program::from_res(res, "shaders/skybox")?;
+    program.upload_texture_slot("skybox", 0)?;
+    Ok(Skybox {
+      program,
+      mesh: cube_mesh(),
+      cubemap,
+    })
+  }
+  /// 加载方式见`Cubemap::from_res`
+  pub fn from_res(res: &Resources, name: &str) -> Result<Skybox, anyhow::Error> {
+    Self::new(res, Cubemap::from_res(res, name)?)
+  }
+  pub fn cubemap(&self) -> &Cubemap {
+    &self.cubemap
+  }
+
+  /// 天空盒的深度恒为1.0，深度测试改为LEQUAL后只会填充没有被物体覆盖的像素
+  pub fn render(&self, camera: &Camera, aspect: f32) -> Result<(), render_gl::Error> {
+    // 去除平移后，天空盒始终围绕着摄像机
+    let view_proj = camera.get_proj_mat(aspect) * camera.get_rotation_view_mat();
+    self.program.set_used();
+    self.program.upload_mat4("view_proj", &view_proj)?;
+    unsafe {
+      GL.depth_func(glow::LEQUAL);
+      GL.depth_mask(false);
+    }
+    unsafe {
+      GL.active_texture(glow::TEXTURE0);
+    }
+    self.cubemap.bind();
+    self.mesh.draw();
+    self.cubemap.detach();
+    self.program.detach();
+    unsafe {
+      GL.depth_mask(true);
+      GL.depth_func(glow::LESS);
+    }
+    Ok(())
+  }
+}
@@ -1,10 +1,15 @@
-use std::{io, path::PathBuf};
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
 
 use glow::HasContext;
 use thiserror::Error;
 
+use crate::render_gl::compressed::{self, Container};
+use crate::render_gl::sampler::{ColorSpace, SamplerDesc};
 use crate::{resources::Resources, GL};
+use image::codecs::hdr::{self, HdrDecoder};
 use image::io::Reader as ImageReader;
+use image::{DynamicImage, Rgb32FImage};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -15,52 +20,102 @@ pub enum Error {
 }
 pub struct Texture {
   inner: glow::Texture,
+  mipmapped: bool,
+  // 以压缩格式上传的纹理无法由GL生成mipmap
+  compressed: bool,
 }
 impl Texture {
   pub fn new(path: PathBuf) -> Result<Texture, Error> {
-    let img = ImageReader::open(path)?.decode().unwrap();
+    Self::new_with(path, &SamplerDesc::default())
+  }
+  /// 除常见的图片格式外，也可读取带有预生成mip链的KTX2与DDS文件
+  pub fn new_with(path: PathBuf, sampler: &SamplerDesc) -> Result<Texture, Error> {
+    let bytes = std::fs::read(&path)?;
+    if compressed::is_container(&bytes) {
+      let container =
+        compressed::parse(&bytes).map_err(|e| Error::LoadError(format!("{:?} {}", path, e)))?;
+      return Self::from_container(&container, sampler)
+        .map_err(|e| Error::LoadError(format!("{:?} {}", path, e)));
+    }
+    Self::from_image_with(decode_image(bytes, &path)?, sampler)
+  }
+  fn from_container(container: &Container, sampler: &SamplerDesc) -> Result<Texture, String> {
+    let inner = unsafe { GL.create_texture().unwrap() };
+    let mut texture = Texture {
+      inner,
+      mipmapped: container.levels.len() > 1,
+      compressed: false,
+    };
+    texture.bind();
+    texture.compressed = unsafe { compressed::upload(container)? };
+    texture.set_sampler(sampler);
+    Ok(texture)
+  }
+  pub fn from_image(img: DynamicImage) -> Result<Texture, Error> {
+    Self::from_image_with(img, &SamplerDesc::default())
+  }
+  /// 由已解码的图片创建纹理，按图片的通道数与位深选择内部格式
+  pub fn from_image_with(img: DynamicImage, sampler: &SamplerDesc) -> Result<Texture, Error> {
+    let (img, format) = PixelFormat::normalize(img, sampler.color_space);
 
     let texture = unsafe { GL.create_texture().unwrap() };
     unsafe {
       GL.bind_texture(glow::TEXTURE_2D, Some(texture));
-      GL.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
-      GL.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
-      GL.tex_parameter_i32(
-        glow::TEXTURE_2D,
-        glow::TEXTURE_MIN_FILTER,
-        glow::LINEAR as i32,
-      );
-      GL.tex_parameter_i32(
+      upload_texture_data(
         glow::TEXTURE_2D,
-        glow::TEXTURE_MAG_FILTER,
-        glow::LINEAR as i32,
+        img.width(),
+        img.height(),
+        &format,
+        img.as_bytes(),
       );
+      format.apply_swizzle(glow::TEXTURE_2D);
     }
-
-    unsafe {
-      match img {
-        image::DynamicImage::ImageRgb8(_)
-        | image::DynamicImage::ImageRgb16(_)
-        | image::DynamicImage::ImageRgb32F(_) => {
-          upload_texture_data(img.width(), img.height(), 3, img.as_bytes())
-        }
-        image::DynamicImage::ImageRgba8(_)
-        | image::DynamicImage::ImageRgba16(_)
-        | image::DynamicImage::ImageRgba32F(_) => {
-          upload_texture_data(img.width(), img.height(), 4, img.as_bytes())
-        }
-        _ => unimplemented!(),
+    let mut texture = Texture {
+      inner: texture,
+      mipmapped: false,
+      compressed: false,
+    };
+    texture.set_sampler(sampler);
+    Ok(texture)
+  }
+  /// 修改纹理自身的采样参数，需要时生成mipmap
+  pub fn set_sampler(&mut self, sampler: &SamplerDesc) {
+    self.bind();
+    if sampler.mipmap.is_some() && !self.mipmapped {
+      if self.compressed {
+        // 没有mip链的压缩纹理只能不使用mipmap
+        sampler.mipmap(None).apply_to_texture(glow::TEXTURE_2D);
+        return;
       }
+      unsafe {
+        // 单层的容器纹理上传时MAX_LEVEL被设为0，需恢复为默认值才能生成完整的mip链
+        GL.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, 1000);
+        GL.generate_mipmap(glow::TEXTURE_2D);
+      }
+      self.mipmapped = true;
     }
-    unsafe {
-      GL.generate_mipmap(glow::TEXTURE_2D);
-    }
-    Ok(Texture { inner: texture })
+    sampler.apply_to_texture(glow::TEXTURE_2D);
+  }
+  /// 1x1的白色纹理，用作无纹理材质的占位
+  pub fn white() -> Result<Texture, Error> {
+    Self::from_image(DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+      1,
+      1,
+      image::Rgba([255, 255, 255, 255]),
+    )))
   }
   pub fn from_res(res: &Resources, name: &str) -> Result<Texture, Error> {
-    let mut full_path = res.get_root_path().clone();
-    full_path.push(name);
-    Self::new(full_path)
+    Self::new(res.resolve_path(name))
+  }
+  pub fn from_res_with(
+    res: &Resources,
+    name: &str,
+    sampler: &SamplerDesc,
+  ) -> Result<Texture, Error> {
+    Self::new_with(res.resolve_path(name), sampler)
+  }
+  pub(super) fn raw(&self) -> glow::Texture {
+    self.inner
   }
   pub fn bind(&self) {
     unsafe {
@@ -81,36 +136,194 @@ impl Drop for Texture {
   }
 }
 
-unsafe fn upload_texture_data(width: u32, height: u32, channels: i32, pixels: &[u8]) {
-  match channels {
-    3 => {
-      GL.tex_image_2d(
-        glow::TEXTURE_2D,
-        0,
-        glow::RGB as i32,
-        width as i32,
-        height as i32,
-        0,
+/// 按文件内容识别格式并解码图片
+pub(super) fn load_image(path: &Path) -> Result<DynamicImage, Error> {
+  decode_image(std::fs::read(path)?, path)
+}
+
+/// Radiance HDR 解码为浮点图片，其余格式交给ImageReader
+/// (ImageReader会将HDR色调映射为8位)
+fn decode_image(bytes: Vec<u8>, path: &Path) -> Result<DynamicImage, Error> {
+  let load_error = |e: image::ImageError| Error::LoadError(format!("{:?} {}", path, e));
+  if bytes.starts_with(hdr::SIGNATURE) {
+    let decoder = HdrDecoder::new(Cursor::new(bytes)).map_err(load_error)?;
+    let meta = decoder.metadata();
+    let pixels = decoder.read_image_hdr().map_err(load_error)?;
+    let pixels = pixels.iter().flat_map(|pixel| pixel.0).collect();
+    return Rgb32FImage::from_raw(meta.width, meta.height, pixels)
+      .map(DynamicImage::ImageRgb32F)
+      .ok_or_else(|| Error::LoadError(format!("{:?} HDR数据长度不符", path)));
+  }
+  ImageReader::new(Cursor::new(bytes))
+    .with_guessed_format()?
+    .decode()
+    .map_err(load_error)
+}
+
+/// 图片在显存中的内部格式，以及像素数据的格式与类型
+pub(super) struct PixelFormat {
+  pub(super) internal: u32,
+  pub(super) format: u32,
+  pub(super) ty: u32,
+  // 每像素字节数
+  size: u32,
+  // 灰度图需将R通道扩展到RGB
+  swizzle: Option<[u32; 4]>,
+}
+impl PixelFormat {
+  const GRAY: [u32; 4] = [glow::RED, glow::RED, glow::RED, glow::ONE];
+  const GRAY_ALPHA: [u32; 4] = [glow::RED, glow::RED, glow::RED, glow::GREEN];
+
+  /// color_space只影响8位RGB(A)图片
+  pub(super) fn of(img: &DynamicImage, color_space: ColorSpace) -> Option<PixelFormat> {
+    let srgb = color_space == ColorSpace::Srgb;
+    let (internal, format, ty, size, swizzle) = match img {
+      DynamicImage::ImageLuma8(_) => (
+        glow::R8,
+        glow::RED,
+        glow::UNSIGNED_BYTE,
+        1,
+        Some(Self::GRAY),
+      ),
+      DynamicImage::ImageLumaA8(_) => (
+        glow::RG8,
+        glow::RG,
+        glow::UNSIGNED_BYTE,
+        2,
+        Some(Self::GRAY_ALPHA),
+      ),
+      DynamicImage::ImageRgb8(_) => (
+        if srgb { glow::SRGB8 } else { glow::RGB8 },
         glow::RGB,
         glow::UNSIGNED_BYTE,
-        Some(&pixels),
-      );
-    }
-    4 => {
-      GL.tex_image_2d(
-        glow::TEXTURE_2D,
-        0,
-        glow::RGBA as i32,
-        width as i32,
-        height as i32,
-        0,
+        3,
+        None,
+      ),
+      DynamicImage::ImageRgba8(_) => (
+        if srgb {
+          glow::SRGB8_ALPHA8
+        } else {
+          glow::RGBA8
+        },
         glow::RGBA,
         glow::UNSIGNED_BYTE,
-        Some(&pixels),
-      );
+        4,
+        None,
+      ),
+      DynamicImage::ImageLuma16(_) => (
+        glow::R16,
+        glow::RED,
+        glow::UNSIGNED_SHORT,
+        2,
+        Some(Self::GRAY),
+      ),
+      DynamicImage::ImageLumaA16(_) => (
+        glow::RG16,
+        glow::RG,
+        glow::UNSIGNED_SHORT,
+        4,
+        Some(Self::GRAY_ALPHA),
+      ),
+      DynamicImage::ImageRgb16(_) => (glow::RGB16, glow::RGB, glow::UNSIGNED_SHORT, 6, None),
+      DynamicImage::ImageRgba16(_) => (glow::RGBA16, glow::RGBA, glow::UNSIGNED_SHORT, 8, None),
+      DynamicImage::ImageRgb32F(_) => (glow::RGB32F, glow::RGB, glow::FLOAT, 12, None),
+      DynamicImage::ImageRgba32F(_) => (glow::RGBA32F, glow::RGBA, glow::FLOAT, 16, None),
+      _ => return None,
+    };
+    Some(PixelFormat {
+      internal,
+      format,
+      ty,
+      size,
+      swizzle,
+    })
+  }
+  /// 不支持直接上传的格式统一转换为RGBA8
+  pub(super) fn normalize(
+    img: DynamicImage,
+    color_space: ColorSpace,
+  ) -> (DynamicImage, PixelFormat) {
+    let img = match PixelFormat::of(&img, color_space) {
+      Some(_) => img,
+      None => DynamicImage::ImageRgba8(img.to_rgba8()),
+    };
+    let format = PixelFormat::of(&img, color_space).unwrap();
+    (img, format)
+  }
+  /// 图片的行紧密排列，行字节数不是4的倍数时需调整解包对齐
+  pub(super) fn unpack_alignment(&self, width: u32) -> i32 {
+    let row = width * self.size;
+    1 << row.trailing_zeros().min(3)
+  }
+  /// 设置当前绑定到target的纹理的通道重排
+  pub(super) unsafe fn apply_swizzle(&self, target: u32) {
+    if let Some(swizzle) = self.swizzle {
+      let swizzle = swizzle.map(|s| s as i32);
+      GL.tex_parameter_i32_slice(target, glow::TEXTURE_SWIZZLE_RGBA, &swizzle);
     }
-    _ => {
-      panic!("不支持的图片通道数")
+  }
+}
+
+/// target: 上传的目标，如TEXTURE_2D或立方体贴图的某一面
+pub(super) unsafe fn upload_texture_data(
+  target: u32,
+  width: u32,
+  height: u32,
+  format: &PixelFormat,
+  pixels: &[u8],
+) {
+  GL.pixel_store_i32(glow::UNPACK_ALIGNMENT, format.unpack_alignment(width));
+  GL.tex_image_2d(
+    target,
+    0,
+    format.internal as i32,
+    width as i32,
+    height as i32,
+    0,
+    format.format,
+    format.ty,
+    Some(pixels),
+  );
+  GL.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+}
+
+#[cfg(test)]
+mod tests {
+  use image::{GrayImage, Rgb32FImage, RgbImage, RgbaImage};
+
+  use super::*;
+
+  #[test]
+  fn color_space_selects_8bit_internal_format() {
+    let rgb = DynamicImage::ImageRgb8(RgbImage::new(1, 1));
+    let rgba = DynamicImage::ImageRgba8(RgbaImage::new(1, 1));
+    let internal =
+      |img: &DynamicImage, color_space| PixelFormat::of(img, color_space).unwrap().internal;
+    assert_eq!(internal(&rgb, ColorSpace::Linear), glow::RGB8);
+    assert_eq!(internal(&rgb, ColorSpace::Srgb), glow::SRGB8);
+    assert_eq!(internal(&rgba, ColorSpace::Linear), glow::RGBA8);
+    assert_eq!(internal(&rgba, ColorSpace::Srgb), glow::SRGB8_ALPHA8);
+  }
+
+  #[test]
+  fn color_space_ignored_for_other_formats() {
+    let gray = DynamicImage::ImageLuma8(GrayImage::new(1, 1));
+    let hdr = DynamicImage::ImageRgb32F(Rgb32FImage::new(1, 1));
+    for color_space in [ColorSpace::Linear, ColorSpace::Srgb] {
+      assert_eq!(
+        PixelFormat::of(&gray, color_space).unwrap().internal,
+        glow::R8
+      );
+      assert_eq!(
+        PixelFormat::of(&hdr, color_space).unwrap().internal,
+        glow::RGB32F
+      );
     }
   }
+
+  #[test]
+  fn textures_default_to_linear() {
+    assert_eq!(SamplerDesc::default().color_space, ColorSpace::Linear);
+    assert_eq!(SamplerDesc::srgb().color_space, ColorSpace::Srgb);
+  }
 }
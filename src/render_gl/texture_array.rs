@@ -0,0 +1,128 @@
+use glow::HasContext;
+use image::DynamicImage;
+
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture::{load_image, Error, PixelFormat};
+use crate::{resources::Resources, GL};
+
+/// 二维纹理数组，着色器中以sampler2DArray采样，第三个坐标为层序号
+pub struct TextureArray {
+  inner: glow::Texture,
+  width: u32,
+  height: u32,
+  layers: u32,
+}
+impl TextureArray {
+  /// 由若干张相同尺寸的图片创建，每张图片为一层
+  pub fn from_images(
+    images: Vec<DynamicImage>,
+    sampler: &SamplerDesc,
+  ) -> Result<TextureArray, Error> {
+    let first = images
+      .first()
+      .ok_or_else(|| Error::LoadError("纹理数组至少需要一张图片".to_string()))?;
+    let (width, height) = (first.width(), first.height());
+    if let Some(img) = images
+      .iter()
+      .find(|img| img.width() != width || img.height() != height)
+    {
+      return Err(Error::LoadError(format!(
+        "纹理数组的各层尺寸须一致 {}x{} != {}x{}",
+        img.width(),
+        img.height(),
+        width,
+        height
+      )));
+    }
+    // 各层的内部格式也须一致
+    let images = if images.iter().all(|img| img.color() == first.color()) {
+      images
+    } else {
+      images
+        .into_iter()
+        .map(|img| DynamicImage::ImageRgba8(img.to_rgba8()))
+        .collect()
+    };
+    let layers = images.len() as u32;
+    let mut format = None;
+    let mut pixels = Vec::new();
+    for img in images {
+      let (img, layer_format) = PixelFormat::normalize(img, sampler.color_space);
+      pixels.extend_from_slice(img.as_bytes());
+      format = Some(layer_format);
+    }
+    let format = format.unwrap();
+
+    let texture = unsafe { GL.create_texture().unwrap() };
+    unsafe {
+      GL.bind_texture(glow::TEXTURE_2D_ARRAY, Some(texture));
+      GL.pixel_store_i32(glow::UNPACK_ALIGNMENT, format.unpack_alignment(width));
+      GL.tex_image_3d(
+        glow::TEXTURE_2D_ARRAY,
+        0,
+        format.internal as i32,
+        width as i32,
+        height as i32,
+        layers as i32,
+        0,
+        format.format,
+        format.ty,
+        Some(&pixels),
+      );
+      GL.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+      format.apply_swizzle(glow::TEXTURE_2D_ARRAY);
+      if sampler.mipmap.is_some() {
+        GL.generate_mipmap(glow::TEXTURE_2D_ARRAY);
+      }
+      sampler.apply_to_texture(glow::TEXTURE_2D_ARRAY);
+    }
+    Ok(TextureArray {
+      inner: texture,
+      width,
+      height,
+      layers,
+    })
+  }
+  /// names中的顺序即层序号
+  pub fn from_res(res: &Resources, names: &[&str]) -> Result<TextureArray, Error> {
+    Self::from_res_with(res, names, &SamplerDesc::default())
+  }
+  pub fn from_res_with(
+    res: &Resources,
+    names: &[&str],
+    sampler: &SamplerDesc,
+  ) -> Result<TextureArray, Error> {
+    let mut images = Vec::with_capacity(names.len());
+    for name in names {
+      images.push(load_image(&res.resolve_path(name))?);
+    }
+    Self::from_images(images, sampler)
+  }
+
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+  pub fn layers(&self) -> u32 {
+    self.layers
+  }
+  pub fn bind(&self) {
+    unsafe {
+      GL.bind_texture(glow::TEXTURE_2D_ARRAY, Some(self.inner));
+    }
+  }
+  pub fn detach(&self) {
+    unsafe {
+      GL.bind_texture(glow::TEXTURE_2D_ARRAY, None);
+    }
+  }
+}
+impl Drop for TextureArray {
+  fn drop(&mut self) {
+    unsafe {
+      GL.delete_texture(self.inner);
+    }
+  }
+}
@@ -0,0 +1,111 @@
+use glow::HasContext;
+
+use crate::render_gl::buffer::VertexArray;
+use crate::render_gl::frame_buffer::{ColorFormat, Depth, FrameBuffer, FrameBufferDesc};
+use crate::render_gl::post::Error;
+use crate::render_gl::Program;
+use crate::resources::Resources;
+use crate::GL;
+
+/// 色调映射算子，顺序与 shaders/post/tonemap.frag 一致
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+  Reinhard,
+  Aces,
+  // 1 - exp(-color)
+  Exposure,
+}
+impl ToneMapping {
+  pub const ALL: [ToneMapping; 3] = [
+    ToneMapping::Reinhard,
+    ToneMapping::Aces,
+    ToneMapping::Exposure,
+  ];
+  pub fn name(self) -> &'static str {
+    match self {
+      ToneMapping::Reinhard => "Reinhard",
+      ToneMapping::Aces => "ACES",
+      ToneMapping::Exposure => "曝光",
+    }
+  }
+}
+
+/// 将浮点画面色调映射并编码为sRGB，结果存入8位帧缓冲
+pub struct ToneMapper {
+  program: Program,
+  pub operator: ToneMapping,
+  // 映射前乘到颜色上
+  pub exposure: f32,
+  target: FrameBuffer,
+  vao: VertexArray,
+}
+impl ToneMapper {
+  pub fn new(res: &Resources, width: i32, height: i32) -> Result<ToneMapper, Error> {
+    let program = Program::from_stages(
+      res,
+      "shaders/post/tonemap",
+      vec![
+        "shaders/post/fullscreen.vert".to_string(),
+        "shaders/post/tonemap.frag".to_string(),
+      ],
+    )?;
+    program.upload_texture_slot("frame", 0)?;
+    Ok(ToneMapper {
+      program,
+      operator: ToneMapping::Aces,
+      exposure: 1.0,
+      target: Self::create_target(width, height)?,
+      vao: VertexArray::new(),
+    })
+  }
+  fn create_target(width: i32, height: i32) -> Result<FrameBuffer, Error> {
+    Ok(
+      FrameBufferDesc::new(width, height)
+        .color(ColorFormat::Rgba8)
+        .depth(Depth::None)
+        .build()?,
+    )
+  }
+  pub fn resize(&mut self, width: i32, height: i32) -> Result<(), Error> {
+    self.target = Self::create_target(width, height)?;
+    Ok(())
+  }
+
+  /// 映射input并返回存放结果的帧缓冲
+  pub fn apply(&self, input: glow::Texture) -> Result<&FrameBuffer, Error> {
+    self.program.set_used();
+    self.program.upload_float("exposure", self.exposure)?;
+    self
+      .program
+      .upload_int("toneMapping", self.operator as i32)?;
+    self.target.bind();
+    self.vao.bind();
+    unsafe {
+      GL.disable(glow::BLEND);
+      GL.active_texture(glow::TEXTURE0);
+      GL.bind_texture(glow::TEXTURE_2D, Some(input));
+      GL.draw_arrays(glow::TRIANGLES, 0, 3);
+      GL.enable(glow::BLEND);
+    }
+    self.vao.unbind();
+    self.target.detach();
+    Ok(&self.target)
+  }
+  /// 最近一次apply的结果
+  pub fn output(&self) -> &FrameBuffer {
+    &self.target
+  }
+
+  pub fn edit(&mut self, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+      for operator in ToneMapping::ALL {
+        ui.radio_value(&mut self.operator, operator, operator.name());
+      }
+    });
+    ui.add(
+      egui::Slider::new(&mut self.exposure, 0.05..=8.0)
+        .logarithmic(true)
+        .text("曝光"),
+    );
+  }
+}
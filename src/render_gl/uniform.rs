@@ -0,0 +1,122 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+use glow::HasContext;
+use na::{Matrix3, Matrix4, Point3, Vector2, Vector3, Vector4};
+
+use crate::GL;
+
+/// 可上传至uniform的类型
+/// 调用前需先使用(set_used)对应的着色程序
+pub trait UniformValue {
+  fn upload(&self, location: &glow::UniformLocation);
+}
+
+/// 由`Program::uniform`获取的具名uniform句柄
+/// 着色程序热重载后，位置会在下次`Program::set`时重新解析
+pub struct Uniform<T: ?Sized> {
+  pub(super) name: String,
+  pub(super) location: Cell<Option<glow::UniformLocation>>,
+  // 解析位置时着色程序的链接次数
+  pub(super) generation: Cell<u32>,
+  _marker: PhantomData<fn(&T)>,
+}
+impl<T: UniformValue + ?Sized> Uniform<T> {
+  pub(super) fn new(name: &str, location: glow::UniformLocation, generation: u32) -> Self {
+    Uniform {
+      name: name.to_string(),
+      location: Cell::new(Some(location)),
+      generation: Cell::new(generation),
+      _marker: PhantomData,
+    }
+  }
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+}
+
+impl UniformValue for i32 {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_1_i32(Some(location), *self) }
+  }
+}
+impl UniformValue for u32 {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_1_u32(Some(location), *self) }
+  }
+}
+impl UniformValue for bool {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_1_i32(Some(location), *self as i32) }
+  }
+}
+impl UniformValue for f32 {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_1_f32(Some(location), *self) }
+  }
+}
+impl UniformValue for Vector2<f32> {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_2_f32(Some(location), self.x, self.y) }
+  }
+}
+impl UniformValue for Vector3<f32> {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_3_f32(Some(location), self.x, self.y, self.z) }
+  }
+}
+impl UniformValue for Point3<f32> {
+  fn upload(&self, location: &glow::UniformLocation) {
+    self.coords.upload(location)
+  }
+}
+impl UniformValue for Vector4<f32> {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_4_f32(Some(location), self.x, self.y, self.z, self.w) }
+  }
+}
+impl UniformValue for Matrix3<f32> {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_matrix_3_f32_slice(Some(location), false, self.as_slice()) }
+  }
+}
+impl UniformValue for Matrix4<f32> {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_matrix_4_f32_slice(Some(location), false, self.as_slice()) }
+  }
+}
+
+// 数组，location为数组首元素的位置
+impl UniformValue for [i32] {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_1_i32_slice(Some(location), self) }
+  }
+}
+impl UniformValue for [u32] {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_1_u32_slice(Some(location), self) }
+  }
+}
+impl UniformValue for [f32] {
+  fn upload(&self, location: &glow::UniformLocation) {
+    unsafe { GL.uniform_1_f32_slice(Some(location), self) }
+  }
+}
+impl UniformValue for [Vector3<f32>] {
+  fn upload(&self, location: &glow::UniformLocation) {
+    let data: Vec<f32> = self.iter().flat_map(|v| v.iter().copied()).collect();
+    unsafe { GL.uniform_3_f32_slice(Some(location), &data) }
+  }
+}
+impl UniformValue for [Vector4<f32>] {
+  fn upload(&self, location: &glow::UniformLocation) {
+    let data: Vec<f32> = self.iter().flat_map(|v| v.iter().copied()).collect();
+    unsafe { GL.uniform_4_f32_slice(Some(location), &data) }
+  }
+}
+impl UniformValue for [Matrix4<f32>] {
+  fn upload(&self, location: &glow::UniformLocation) {
+    let data: Vec<f32> = self.iter().flat_map(|m| m.iter().copied()).collect();
+    unsafe { GL.uniform_matrix_4_f32_slice(Some(location), false, &data) }
+  }
+}
@@ -22,6 +22,16 @@ impl Resources {
   pub fn get_root_path(&self) -> &PathBuf {
     &self.root_path
   }
+  /// 将资源名(以'/'分隔)解析为文件系统路径
+  pub fn resolve_path(&self, resource_name: &str) -> PathBuf {
+    resource_name_to_path(&self.root_path, resource_name)
+  }
+
+  pub fn from_path(root_path: impl Into<PathBuf>) -> Resources {
+    Resources {
+      root_path: root_path.into(),
+    }
+  }
 
   pub fn from_relative_exe_path(rel_path: &Path) -> Result<Resources, Error> {
     let exe_file_name = ::std::env::current_exe().map_err(|_| Error::FailedToGetExePath)?;
@@ -57,6 +67,22 @@ impl Resources {
 
     Ok(unsafe { String::from_utf8_unchecked(buffer) })
   }
+  /// 列出目录下具有指定扩展名的资源，按名称排序
+  /// dir: 资源目录，如 "scenes"; ext: 不含'.'的扩展名
+  pub fn list_dir(&self, dir: &str, ext: &str) -> Result<Vec<String>, Error> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(resource_name_to_path(&self.root_path, dir))? {
+      let path = entry?.path();
+      if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some(ext) {
+        continue;
+      }
+      if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
+        names.push(format!("{}/{}", dir, file_name));
+      }
+    }
+    names.sort();
+    Ok(names)
+  }
 }
 
 fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
@@ -1,11 +1,14 @@
 use arcstr::ArcStr;
 use glow::HasContext;
 
+use super::graph::{Drawable, SceneGraph, Transform};
 use super::scene::Scene;
-use crate::geom::camera::Camera;
+use crate::geom::camera::{Camera, CAMERA_BLOCK};
 use crate::render_gl::data::*;
 use crate::render_gl::debug::check_error;
-use crate::render_gl::{buffer, texture};
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture_array::TextureArray;
 use crate::resources::Resources;
 use crate::{render_gl, GL};
 
@@ -20,10 +23,8 @@ struct Vertex {
 
 pub struct Cube2 {
   program: render_gl::Program,
-  _vbo: buffer::ArrayBuffer,
-  _ebo: buffer::ElementArrayBuffer,
-  vao: buffer::VertexArray,
-  texture: Vec<texture::Texture>,
+  graph: SceneGraph,
+  textures: TextureArray,
   camera: Camera,
 }
 fn gen_vertices() -> Vec<Vertex> {
@@ -158,61 +159,42 @@ fn gen_indices(vertices: &Vec<Vertex>) -> Vec<u32> {
 impl Cube2 {
   pub fn new(res: &Resources) -> Result<Cube2, anyhow::Error> {
     let program = render_gl::Program::from_res(res, "shaders/cube")?;
+    program.bind_uniform_block(CAMERA_BLOCK)?;
 
     let vertices: Vec<Vertex> = gen_vertices();
     let indices: Vec<u32> = gen_indices(&vertices);
 
-    let vbo = buffer::ArrayBuffer::new();
-    vbo.bind();
-    vbo.static_draw_data(&vertices);
-    vbo.unbind();
-    let ebo = buffer::ElementArrayBuffer::new();
-    ebo.bind();
-    ebo.static_draw_data(&indices);
-    ebo.unbind();
-    let vao = buffer::VertexArray::new();
-
-    vao.bind();
-    vbo.bind();
-    ebo.bind();
-    Vertex::vertex_attrib_pointers();
-    // 注意这里有一个自动绑定机制
-    vao.unbind();
-    let texture0 = texture::Texture::from_res(&res, "textures/container.jpg")?;
-    let texture1 = texture::Texture::from_res(res, "textures/awesomeface.png")?;
+    let mesh = Mesh::new(&vertices, &indices, Vertex::vertex_attrib_pointers);
+    let mut graph = SceneGraph::new();
+    graph.add_drawable("cube", Transform::default(), None, Drawable::Mesh(mesh));
+    // 两张纹理作为纹理数组的第0、1层，只需占用一个纹理单元
+    let textures = TextureArray::from_res_with(
+      res,
+      &["textures/container.jpg", "textures/awesomeface.png"],
+      &SamplerDesc::srgb(),
+    )?;
     //告诉OpenGL每个着色器采样器属于哪个纹理单元
-    program.upload_texture_slot("texture0", 0);
-    program.upload_texture_slot("texture1", 1);
+    program.upload_texture_slot("textures", 0)?;
 
     Ok(Cube2 {
       program,
-      _vbo: vbo,
-      _ebo: ebo,
-      vao,
-      texture: vec![texture0, texture1],
+      graph,
+      textures,
       camera: Camera::new(na::Point3::new(0.0, 0.0, 0.0)),
     })
   }
 }
 impl Scene for Cube2 {
-  fn render(&self, aspect: f32) -> Option<()> {
+  fn render(&self) -> Result<(), anyhow::Error> {
     check_error();
     self.program.set_used();
-    self.vao.bind();
     unsafe {
-      // 绑定两个纹理到对应的纹理单元
       GL.active_texture(glow::TEXTURE0);
-      self.texture.get(0)?.bind();
-      GL.active_texture(glow::TEXTURE1);
-      self.texture.get(1)?.bind();
-      self
-        .program
-        .upload_mat4("vp_proj", &self.camera.get_vp_mat(aspect));
-      GL.draw_elements(glow::TRIANGLES, 36, glow::UNSIGNED_INT, 0);
     }
-    self.vao.unbind();
+    self.textures.bind();
+    self.graph.render(&self.program)?;
     self.program.detach();
-    Some(())
+    Ok(())
   }
 
   fn get_camera(&mut self) -> &mut Camera {
@@ -222,4 +204,8 @@ impl Scene for Cube2 {
   fn get_name(&self) -> ArcStr {
     ArcStr::from("cube")
   }
+
+  fn get_graph(&mut self) -> &mut SceneGraph {
+    &mut self.graph
+  }
 }
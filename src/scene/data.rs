@@ -0,0 +1,289 @@
+use anyhow::Context;
+use arcstr::ArcStr;
+use na::UnitQuaternion;
+use serde::Deserialize;
+
+use super::graph::{Drawable, NodeId, SceneGraph, Transform};
+use super::scene::Scene;
+use crate::geom::camera::{Camera, CAMERA_BLOCK};
+use crate::geom::light::{
+  DirectLight, LightSource, LightsUniform, PointLight, SpotLight, LIGHTS_BLOCK,
+};
+use crate::model;
+use crate::render_gl;
+use crate::render_gl::debug::check_error;
+use crate::render_gl::ibl::Environment;
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture::Texture;
+use crate::resources::Resources;
+
+/// 场景描述文件(RON)的顶层结构
+#[derive(Debug, Deserialize)]
+pub struct SceneDesc {
+  pub name: String,
+  // 需与phong着色器的uniform保持一致
+  #[serde(default = "default_shader")]
+  pub shader: String,
+  #[serde(default)]
+  pub camera: CameraDesc,
+  #[serde(default)]
+  pub nodes: Vec<NodeDesc>,
+  #[serde(default)]
+  pub lights: Vec<LightDesc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CameraDesc {
+  pub eye: [f32; 3],
+}
+impl Default for CameraDesc {
+  /// 位于原点前方，朝-z方向看向原点处的物体
+  fn default() -> Self {
+    CameraDesc {
+      eye: [0.0, 0.0, 3.0],
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeDesc {
+  pub name: String,
+  #[serde(default)]
+  pub translation: [f32; 3],
+  // 欧拉角(角度制) roll, pitch, yaw
+  #[serde(default)]
+  pub rotation: [f32; 3],
+  #[serde(default = "default_scale")]
+  pub scale: [f32; 3],
+  // .gltf/.glb/.obj 模型
+  #[serde(default)]
+  pub mesh: Option<String>,
+  // 替换模型材质的纹理
+  #[serde(default)]
+  pub texture: Option<String>,
+  #[serde(default)]
+  pub children: Vec<NodeDesc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub enum LightDesc {
+  Directional {
+    name: String,
+    direction: [f32; 3],
+    #[serde(default = "default_scale")]
+    color: [f32; 3],
+  },
+  Point {
+    name: String,
+    position: [f32; 3],
+    #[serde(default = "default_scale")]
+    color: [f32; 3],
+    // 衰减的 常数项, 一次项, 二次项
+    #[serde(default = "default_attenuation")]
+    attenuation: [f32; 3],
+  },
+  Spot {
+    name: String,
+    position: [f32; 3],
+    direction: [f32; 3],
+    #[serde(default = "default_scale")]
+    color: [f32; 3],
+    #[serde(default = "default_attenuation")]
+    attenuation: [f32; 3],
+    // 内外切光角(角度制)
+    #[serde(default = "default_cut_off")]
+    cut_off: [f32; 2],
+  },
+}
+
+fn default_shader() -> String {
+  String::from("shaders/phong")
+}
+fn default_scale() -> [f32; 3] {
+  [1.0, 1.0, 1.0]
+}
+fn default_attenuation() -> [f32; 3] {
+  [1.0, 0.0, 0.0]
+}
+fn default_cut_off() -> [f32; 2] {
+  [12.5, 17.5]
+}
+
+impl LightDesc {
+  fn add_to(&self, graph: &mut SceneGraph) -> NodeId {
+    match *self {
+      LightDesc::Directional {
+        ref name,
+        direction,
+        color,
+      } => graph.add_light(
+        name,
+        Transform::default(),
+        None,
+        LightSource::Direct(DirectLight::new(color.into(), direction.into())),
+      ),
+      LightDesc::Point {
+        ref name,
+        position,
+        color,
+        attenuation: [constant, linear, quadratic],
+      } => graph.add_light(
+        name,
+        Transform::from_translation(position.into()),
+        None,
+        LightSource::Point(PointLight {
+          constant,
+          linear,
+          quadratic,
+          ..PointLight::new(color.into())
+        }),
+      ),
+      LightDesc::Spot {
+        ref name,
+        position,
+        direction,
+        color,
+        attenuation: [constant, linear, quadratic],
+        cut_off: [cut_off, outer_cut_off],
+      } => graph.add_light(
+        name,
+        Transform::from_translation(position.into()),
+        None,
+        LightSource::Spot(SpotLight {
+          constant,
+          linear,
+          quadratic,
+          cut_off,
+          outer_cut_off,
+          ..SpotLight::new(color.into(), direction.into())
+        }),
+      ),
+    }
+  }
+}
+
+impl NodeDesc {
+  fn transform(&self) -> Transform {
+    let [roll, pitch, yaw] = self.rotation;
+    Transform {
+      translation: self.translation.into(),
+      rotation: UnitQuaternion::from_euler_angles(
+        roll.to_radians(),
+        pitch.to_radians(),
+        yaw.to_radians(),
+      ),
+      scale: self.scale.into(),
+    }
+  }
+}
+
+/// 由场景描述文件构建的场景
+pub struct DataScene {
+  name: ArcStr,
+  program: render_gl::Program,
+  graph: SceneGraph,
+  lights: LightsUniform,
+  camera: Camera,
+}
+
+impl DataScene {
+  /// name: 场景文件的资源名，如 "scenes/gltf_box.ron"
+  pub fn from_res(res: &Resources, name: &str) -> Result<DataScene, anyhow::Error> {
+    let source = res.load_string(name)?;
+    let desc: SceneDesc =
+      ron::from_str(&source).with_context(|| format!("场景文件解析失败 {}", name))?;
+    Self::from_desc(res, desc).with_context(|| format!("场景构建失败 {}", name))
+  }
+
+  pub fn from_desc(res: &Resources, desc: SceneDesc) -> Result<DataScene, anyhow::Error> {
+    let program = render_gl::Program::from_res(res, &desc.shader)?;
+    // 场景文件可指定任意着色器，调试时报告其缺少的uniform与uniform块
+    program.set_strict(cfg!(debug_assertions));
+    program.bind_uniform_block(CAMERA_BLOCK)?;
+    program.bind_uniform_block(LIGHTS_BLOCK)?;
+    Environment::bind_program(&program)?;
+    //告诉OpenGL每个着色器采样器属于哪个纹理单元
+    program.upload_texture_slot("texture0", 0)?;
+
+    let mut graph = SceneGraph::new();
+    for node in &desc.nodes {
+      add_node(res, &mut graph, node, None)?;
+    }
+    for light in &desc.lights {
+      light.add_to(&mut graph);
+    }
+
+    Ok(DataScene {
+      name: ArcStr::from(desc.name),
+      program,
+      graph,
+      lights: LightsUniform::new()?,
+      camera: Camera::new(desc.camera.eye.into()),
+    })
+  }
+}
+
+fn add_node(
+  res: &Resources,
+  graph: &mut SceneGraph,
+  desc: &NodeDesc,
+  parent: Option<NodeId>,
+) -> Result<(), anyhow::Error> {
+  let id = match &desc.mesh {
+    Some(mesh) => {
+      let mut model = model::load(res, mesh)?;
+      if let Some(texture) = &desc.texture {
+        model.set_texture(Texture::from_res_with(res, texture, &SamplerDesc::srgb())?);
+      }
+      graph.add_drawable(&desc.name, desc.transform(), parent, Drawable::Model(model))
+    }
+    None => graph.add_node(&desc.name, desc.transform(), parent),
+  };
+  for child in &desc.children {
+    add_node(res, graph, child, Some(id))?;
+  }
+  Ok(())
+}
+
+impl Scene for DataScene {
+  fn render(&self) -> Result<(), anyhow::Error> {
+    check_error();
+    self.program.set_used();
+    self.lights.update(self.graph.lights());
+    self.graph.render(&self.program)?;
+    self.program.detach();
+    Ok(())
+  }
+
+  fn get_camera(&mut self) -> &mut Camera {
+    &mut self.camera
+  }
+
+  fn get_name(&self) -> ArcStr {
+    self.name.clone()
+  }
+
+  fn get_graph(&mut self) -> &mut SceneGraph {
+    &mut self.graph
+  }
+
+  fn render_window(&mut self, egui_ctx: &egui::CtxRef) {
+    egui::Window::new(format!("{} 光照设置", self.name))
+      .resizable(false)
+      .show(egui_ctx, |ui| {
+        self.graph.edit_lights(ui);
+      });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn camera_defaults_in_front_of_origin() {
+    let desc: SceneDesc = ron::from_str(r#"(name: "empty")"#).unwrap();
+    assert_eq!(desc.camera.eye, [0.0, 0.0, 3.0]);
+    assert!(desc.nodes.is_empty());
+  }
+}
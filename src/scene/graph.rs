@@ -0,0 +1,235 @@
+use na::{Matrix3, Matrix4, Point3, UnitQuaternion, Vector3};
+
+use another::ui;
+
+use crate::geom::light::LightSource;
+use crate::model::Model;
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::{self, Program};
+
+pub type NodeId = usize;
+
+/// 局部的 平移/旋转/缩放 变换
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+  pub translation: Vector3<f32>,
+  pub rotation: UnitQuaternion<f32>,
+  pub scale: Vector3<f32>,
+}
+impl Transform {
+  pub fn from_translation(translation: Vector3<f32>) -> Self {
+    Transform {
+      translation,
+      ..Default::default()
+    }
+  }
+  // 先缩放，再旋转，最后平移
+  pub fn to_matrix(&self) -> Matrix4<f32> {
+    Matrix4::new_translation(&self.translation)
+      * self.rotation.to_homogeneous()
+      * Matrix4::new_nonuniform_scaling(&self.scale)
+  }
+}
+impl Default for Transform {
+  fn default() -> Self {
+    Transform {
+      translation: Vector3::zeros(),
+      rotation: UnitQuaternion::identity(),
+      scale: Vector3::new(1.0, 1.0, 1.0),
+    }
+  }
+}
+
+/// 挂载在节点上的可绘制对象
+pub enum Drawable {
+  // 纹理由场景自行绑定
+  Mesh(Mesh),
+  // 使用模型自带的材质
+  Model(Model),
+}
+
+pub struct Node {
+  pub name: String,
+  transform: Transform,
+  parent: Option<NodeId>,
+  children: Vec<NodeId>,
+  // 缓存的世界变换矩阵
+  world: Matrix4<f32>,
+  dirty: bool,
+  pub drawable: Option<Drawable>,
+  pub light: Option<LightSource>,
+}
+impl Node {
+  pub fn transform(&self) -> &Transform {
+    &self.transform
+  }
+  pub fn parent(&self) -> Option<NodeId> {
+    self.parent
+  }
+  pub fn children(&self) -> &[NodeId] {
+    &self.children
+  }
+  pub fn world_matrix(&self) -> &Matrix4<f32> {
+    &self.world
+  }
+  pub fn world_position(&self) -> Point3<f32> {
+    Point3::from(self.world.fixed_slice::<3, 1>(0, 3).into_owned())
+  }
+}
+
+/// 以父子关系组织的场景节点树
+#[derive(Default)]
+pub struct SceneGraph {
+  nodes: Vec<Node>,
+  roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add_node(&mut self, name: &str, transform: Transform, parent: Option<NodeId>) -> NodeId {
+    let id = self.nodes.len();
+    self.nodes.push(Node {
+      name: name.to_string(),
+      transform,
+      parent,
+      children: Vec::new(),
+      world: Matrix4::identity(),
+      dirty: true,
+      drawable: None,
+      light: None,
+    });
+    match parent {
+      Some(parent) => self.nodes[parent].children.push(id),
+      None => self.roots.push(id),
+    }
+    id
+  }
+
+  pub fn add_drawable(
+    &mut self,
+    name: &str,
+    transform: Transform,
+    parent: Option<NodeId>,
+    drawable: Drawable,
+  ) -> NodeId {
+    let id = self.add_node(name, transform, parent);
+    self.nodes[id].drawable = Some(drawable);
+    id
+  }
+
+  pub fn add_light(
+    &mut self,
+    name: &str,
+    transform: Transform,
+    parent: Option<NodeId>,
+    light: LightSource,
+  ) -> NodeId {
+    let id = self.add_node(name, transform, parent);
+    self.nodes[id].light = Some(light);
+    id
+  }
+
+  pub fn node(&self, id: NodeId) -> &Node {
+    &self.nodes[id]
+  }
+  pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+    &mut self.nodes[id]
+  }
+  pub fn find(&self, name: &str) -> Option<NodeId> {
+    self.nodes.iter().position(|node| node.name == name)
+  }
+
+  /// 获取可修改的局部变换，节点的世界矩阵会在下次update_world时重新计算
+  pub fn transform_mut(&mut self, id: NodeId) -> &mut Transform {
+    let node = &mut self.nodes[id];
+    node.dirty = true;
+    &mut node.transform
+  }
+  pub fn set_transform(&mut self, id: NodeId, transform: Transform) {
+    *self.transform_mut(id) = transform;
+  }
+
+  /// 自根节点向下刷新被修改过的节点及其子树的世界矩阵
+  pub fn update_world(&mut self) {
+    for i in 0..self.roots.len() {
+      let root = self.roots[i];
+      self.update_node(root, &Matrix4::identity(), false);
+    }
+  }
+  fn update_node(&mut self, id: NodeId, parent_world: &Matrix4<f32>, parent_dirty: bool) {
+    let node = &mut self.nodes[id];
+    let dirty = parent_dirty || node.dirty;
+    if dirty {
+      node.world = parent_world * node.transform.to_matrix();
+      node.dirty = false;
+    }
+    let world = node.world;
+    for i in 0..self.nodes[id].children.len() {
+      let child = self.nodes[id].children[i];
+      self.update_node(child, &world, dirty);
+    }
+  }
+
+  /// 深度优先遍历并绘制所有节点，上传m_proj与NormalMat
+  pub fn render(&self, program: &Program) -> Result<(), render_gl::Error> {
+    for root in &self.roots {
+      self.render_node(*root, program)?;
+    }
+    Ok(())
+  }
+  fn render_node(&self, id: NodeId, program: &Program) -> Result<(), render_gl::Error> {
+    let node = &self.nodes[id];
+    match &node.drawable {
+      Some(Drawable::Mesh(mesh)) => {
+        let nor_mat: Matrix3<f32> = node
+          .world
+          .fixed_resize::<3, 3>(0.0)
+          .try_inverse()
+          .unwrap_or_else(Matrix3::identity)
+          .transpose();
+        program.upload_mat4("m_proj", &node.world)?;
+        program.upload_mat3("NormalMat", &nor_mat)?;
+        mesh.draw();
+      }
+      Some(Drawable::Model(model)) => model.render(program, &node.world)?,
+      None => {}
+    }
+    for child in &node.children {
+      self.render_node(*child, program)?;
+    }
+    Ok(())
+  }
+
+  /// 变换到世界空间的所有光源
+  pub fn lights(&self) -> impl Iterator<Item = LightSource> + '_ {
+    self
+      .nodes
+      .iter()
+      .filter_map(|node| node.light.as_ref().map(|light| light.to_world(&node.world)))
+  }
+  pub fn light_nodes(&self) -> Vec<NodeId> {
+    (0..self.nodes.len())
+      .filter(|id| self.nodes[*id].light.is_some())
+      .collect()
+  }
+
+  /// 逐个编辑光源参数与所在节点的位置
+  pub fn edit_lights(&mut self, ui: &mut egui::Ui) {
+    for id in self.light_nodes() {
+      let node = &mut self.nodes[id];
+      let light = node.light.as_mut().unwrap();
+      let title = format!("{} ({})", node.name, light.kind_name());
+      ui.collapsing(title, |ui| {
+        light.edit(ui);
+        if !matches!(light, LightSource::Direct(_)) {
+          ui.label("位置");
+          ui::edit_vec3(ui, &mut node.transform.translation, -20.0..=20.0);
+          node.dirty = true;
+        }
+      });
+    }
+  }
+}
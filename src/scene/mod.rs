@@ -1,4 +1,71 @@
 pub mod cube;
+pub mod data;
+pub mod graph;
 pub mod phong;
 pub mod scene;
 pub mod spin;
+
+use glow::HasContext;
+
+use crate::geom::camera::CameraUniform;
+use crate::render_gl::ibl::Environment;
+use crate::render_gl::offscreen::OffScreen;
+use crate::render_gl::skybox::Skybox;
+use crate::resources::Resources;
+use crate::GL;
+use scene::Scene;
+
+/// 内置场景，以及 assets/scenes 下的所有场景描述文件
+pub fn load_scenes(res: &Resources) -> Result<Vec<Box<dyn Scene>>, anyhow::Error> {
+  let mut scenes: Vec<Box<dyn Scene>> = vec![
+    Box::new(spin::Cube::new(res)?),
+    Box::new(cube::Cube2::new(res)?),
+    Box::new(phong::Cube::new(res)?),
+  ];
+  for name in res.list_dir("scenes", "ron")? {
+    scenes.push(Box::new(data::DataScene::from_res(res, &name)?));
+  }
+  Ok(scenes)
+}
+
+/// 按名称或索引查找场景
+pub fn find_scene(scenes: &[Box<dyn Scene>], key: &str) -> Option<usize> {
+  scenes
+    .iter()
+    .position(|scene| scene.get_name() == key)
+    .or_else(|| key.parse().ok().filter(|index| *index < scenes.len()))
+}
+
+/// 更新场景并将其渲染到离屏帧缓冲，再执行后处理，窗口与无窗口模式共用
+/// environment提供场景的环境光照，skybox为Some时在场景之后绘制天空盒
+pub fn render_frame(
+  scene: &mut dyn Scene,
+  offscreen: &OffScreen,
+  camera_uniform: &CameraUniform,
+  environment: &Environment,
+  skybox: Option<&Skybox>,
+  aspect: f32,
+) -> Result<(), anyhow::Error> {
+  scene.update();
+  scene.get_graph().update_world();
+  offscreen.bind();
+  unsafe {
+    GL.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+    GL.enable(glow::DEPTH_TEST);
+  }
+  camera_uniform.update(scene.get_camera(), aspect);
+  environment.bind();
+  // 出错时仍需恢复帧缓冲与深度测试
+  let result = scene.render().and_then(|()| {
+    if let Some(skybox) = skybox {
+      skybox.render(scene.get_camera(), aspect)?;
+    }
+    Ok(())
+  });
+  offscreen.detach();
+  unsafe {
+    GL.disable(glow::DEPTH_TEST);
+  }
+  result?;
+  Ok(offscreen.post_process()?)
+}
@@ -1,12 +1,19 @@
-use another::ui;
+use anyhow::Context;
 use arcstr::ArcStr;
 use glow::HasContext;
 
+use super::graph::{Drawable, SceneGraph, Transform};
 use super::scene::Scene;
-use crate::geom::camera::Camera;
+use crate::geom::camera::{Camera, CAMERA_BLOCK};
+use crate::geom::light::{
+  DirectLight, LightSource, LightsUniform, PointLight, SpotLight, LIGHTS_BLOCK,
+};
 use crate::render_gl::data::*;
 use crate::render_gl::debug::check_error;
-use crate::render_gl::{buffer, texture};
+use crate::render_gl::ibl::Environment;
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture;
 use crate::resources::Resources;
 use crate::{render_gl, GL};
 
@@ -23,13 +30,10 @@ struct Vertex {
 
 pub struct Cube {
   program: render_gl::Program,
-  _vbo: buffer::ArrayBuffer,
-  _ebo: buffer::ElementArrayBuffer,
-  vao: buffer::VertexArray,
+  graph: SceneGraph,
+  lights: LightsUniform,
   texture: Vec<texture::Texture>,
   camera: Camera,
-  light_pos: na::Vector3<f32>,
-  light_color: na::Vector3<f32>,
 }
 fn gen_vertices() -> Vec<Vertex> {
   vec![
@@ -193,73 +197,70 @@ fn gen_indices(vertices: &Vec<Vertex>) -> Vec<u32> {
 impl Cube {
   pub fn new(res: &Resources) -> Result<Cube, anyhow::Error> {
     let program = render_gl::Program::from_res(res, "shaders/phong")?;
+    program.bind_uniform_block(CAMERA_BLOCK)?;
+    program.bind_uniform_block(LIGHTS_BLOCK)?;
+    Environment::bind_program(&program)?;
 
     let vertices: Vec<Vertex> = gen_vertices();
     let indices: Vec<u32> = gen_indices(&vertices);
 
-    let vbo = buffer::ArrayBuffer::new();
-    vbo.bind();
-    vbo.static_draw_data(&vertices);
-    vbo.unbind();
-    let ebo = buffer::ElementArrayBuffer::new();
-    ebo.bind();
-    ebo.static_draw_data(&indices);
-    ebo.unbind();
-    let vao = buffer::VertexArray::new();
-
-    vao.bind();
-    vbo.bind();
-    ebo.bind();
-    Vertex::vertex_attrib_pointers();
-    // 注意这里有一个自动绑定机制
-    vao.unbind();
-    let texture0 = texture::Texture::from_res(&res, "textures/container.jpg")?;
+    let mesh = Mesh::new(&vertices, &indices, Vertex::vertex_attrib_pointers);
+    let mut graph = SceneGraph::new();
+    graph.add_drawable("cube", Transform::default(), None, Drawable::Mesh(mesh));
+    graph.add_light(
+      "light",
+      Transform::from_translation(na::Vector3::new(10.0, 10.0, 10.0)),
+      None,
+      LightSource::Point(PointLight::new(na::Vector3::new(1.0, 1.0, 1.0))),
+    );
+    graph.add_light(
+      "sun",
+      Transform::default(),
+      None,
+      LightSource::Direct(DirectLight::new(
+        na::Vector3::new(0.2, 0.2, 0.3),
+        na::Vector3::new(-1.0, -1.0, -0.5),
+      )),
+    );
+    graph.add_light(
+      "spot",
+      Transform::from_translation(na::Vector3::new(0.0, 15.0, 0.0)),
+      None,
+      LightSource::Spot(SpotLight::new(
+        na::Vector3::new(1.0, 0.6, 0.2),
+        na::Vector3::new(0.0, -1.0, 0.0),
+      )),
+    );
+    let texture0 =
+      texture::Texture::from_res_with(&res, "textures/container.jpg", &SamplerDesc::srgb())?;
     //告诉OpenGL每个着色器采样器属于哪个纹理单元
-    program.upload_texture_slot("texture0", 0);
+    program.upload_texture_slot("texture0", 0)?;
 
     Ok(Cube {
       program,
-      _vbo: vbo,
-      _ebo: ebo,
-      vao,
+      graph,
+      lights: LightsUniform::new()?,
       texture: vec![texture0],
       camera: Camera::new(na::Point3::new(0.0, 0.0, 0.0)),
-      light_pos: na::Vector3::<f32>::new(10.0, 10.0, 10.0),
-      light_color: na::Vector3::<f32>::new(1.0, 1.0, 1.0),
     })
   }
 }
 impl Scene for Cube {
-  fn render(&self, aspect: f32) -> Option<()> {
+  fn render(&self) -> Result<(), anyhow::Error> {
     check_error();
     self.program.set_used();
-    self.vao.bind();
-    let model_mat = na::Matrix4::<f32>::identity();
-    let view_mat = self.camera.get_view_mat();
-    let proj_mat = self.camera.get_proj_mat(aspect);
-
-    let nor_mat = model_mat
-      .fixed_resize::<3, 3>(0.0)
-      .try_inverse()
-      .unwrap()
-      .transpose();
     unsafe {
       // 绑定纹理到对应的纹理单元
       GL.active_texture(glow::TEXTURE0);
-      self.texture.get(0)?.bind();
-      self.program.upload_mat4("vp_proj", &(proj_mat * view_mat));
-      self.program.upload_mat4("m_proj", &model_mat);
-      self
-        .program
-        .upload_mat3("NormalMat", &nor_mat.try_into().unwrap());
-      self.program.upload_vec3("lightPos", &self.light_pos);
-      self.program.upload_vec3("lightColor", &self.light_color);
-      self.program.upload_vec3("viewPos", &self.camera.eye.coords);
-      GL.draw_elements(glow::TRIANGLES, 36, glow::UNSIGNED_INT, 0);
+      self.texture.first().context("缺少纹理")?.bind();
     }
-    self.vao.unbind();
+    self.lights.update(self.graph.lights());
+    self
+      .program
+      .upload_vec4("baseColor", &na::Vector4::new(1.0, 1.0, 1.0, 1.0))?;
+    self.graph.render(&self.program)?;
     self.program.detach();
-    Some(())
+    Ok(())
   }
 
   fn get_camera(&mut self) -> &mut Camera {
@@ -270,19 +271,15 @@ impl Scene for Cube {
     ArcStr::from("phong")
   }
 
+  fn get_graph(&mut self) -> &mut SceneGraph {
+    &mut self.graph
+  }
+
   fn render_window(&mut self, egui_ctx: &egui::CtxRef) {
     egui::Window::new("Phong光照设置")
       .resizable(false)
       .show(&egui_ctx, |ui| {
-        ui.horizontal(|ui| {
-          ui.label("点光源颜色");
-          let light = self.light_color.as_mut_slice();
-          ui.color_edit_button_rgb(light.try_into().unwrap())
-        });
-        ui.horizontal(|ui| {
-          ui.label("点光源位置");
-          ui::edit_vec3(ui, &mut self.light_pos, 5.0..=20.0);
-        });
+        self.graph.edit_lights(ui);
       });
   }
 }
@@ -1,7 +1,11 @@
 use crate::geom::camera::Camera;
+use crate::scene::graph::SceneGraph;
 pub trait Scene {
-  fn render(&self, aspect: f32) -> Option<()>;
+  fn render(&self) -> Result<(), anyhow::Error>;
   fn get_camera(&mut self) -> &mut Camera;
   fn get_name(&self) -> arcstr::ArcStr;
+  fn get_graph(&mut self) -> &mut SceneGraph;
+  // 每帧渲染前调用，用于修改节点变换等
+  fn update(&mut self) {}
   fn render_window(&mut self, _: &egui::CtxRef) {}
 }
@@ -1,12 +1,16 @@
+use anyhow::Context;
 use arcstr::ArcStr;
 use glow::HasContext;
-use na::Matrix4;
+use na::UnitQuaternion;
 
+use super::graph::{Drawable, NodeId, SceneGraph, Transform};
 use super::scene::Scene;
-use crate::geom::camera::Camera;
+use crate::geom::camera::{Camera, CAMERA_BLOCK};
 use crate::render_gl::data::*;
 use crate::render_gl::debug::check_error;
-use crate::render_gl::{buffer, texture};
+use crate::render_gl::mesh::Mesh;
+use crate::render_gl::sampler::SamplerDesc;
+use crate::render_gl::texture;
 use crate::resources::Resources;
 use crate::{render_gl, time, GL};
 
@@ -21,9 +25,8 @@ struct Vertex {
 
 pub struct Cube {
   program: render_gl::Program,
-  _vbo: buffer::ArrayBuffer,
-  _ebo: buffer::ElementArrayBuffer,
-  vao: buffer::VertexArray,
+  graph: SceneGraph,
+  cube: NodeId,
   texture: Vec<texture::Texture>,
   camera: Camera,
 }
@@ -159,63 +162,49 @@ fn gen_indices(vertices: &Vec<Vertex>) -> Vec<u32> {
 impl Cube {
   pub fn new(res: &Resources) -> Result<Cube, anyhow::Error> {
     let program = render_gl::Program::from_res(res, "shaders/spin")?;
+    program.bind_uniform_block(CAMERA_BLOCK)?;
 
     let vertices: Vec<Vertex> = gen_vertices();
     let indices: Vec<u32> = gen_indices(&vertices);
 
-    let vbo = buffer::ArrayBuffer::new();
-    vbo.bind();
-    vbo.static_draw_data(&vertices);
-    vbo.unbind();
-    let ebo = buffer::ElementArrayBuffer::new();
-    ebo.bind();
-    ebo.static_draw_data(&indices);
-    ebo.unbind();
-    let vao = buffer::VertexArray::new();
-
-    vao.bind();
-    vbo.bind();
-    ebo.bind();
-    Vertex::vertex_attrib_pointers();
-    // 注意这里有一个自动绑定机制
-    vao.unbind();
-    let texture0 = texture::Texture::from_res(&res, "textures/container.jpg")?;
+    let mesh = Mesh::new(&vertices, &indices, Vertex::vertex_attrib_pointers);
+    let mut graph = SceneGraph::new();
+    let cube = graph.add_drawable("cube", Transform::default(), None, Drawable::Mesh(mesh));
+    let texture0 =
+      texture::Texture::from_res_with(&res, "textures/container.jpg", &SamplerDesc::srgb())?;
     //告诉OpenGL每个着色器采样器属于哪个纹理单元
-    program.upload_texture_slot("texture0", 0);
+    program.upload_texture_slot("texture0", 0)?;
 
     Ok(Cube {
       program,
-      _vbo: vbo,
-      _ebo: ebo,
-      vao,
+      graph,
+      cube,
       texture: vec![texture0],
       camera: Camera::new(na::Point3::new(0.0, 0.0, 0.0)),
     })
   }
 }
 impl Scene for Cube {
-  fn render(&self, aspect: f32) -> Option<()> {
+  fn update(&mut self) {
     let time = time::get_now();
     let angel_x = (2.3 * time).sin();
     let angel_y = (0.3 * time).sin();
     let angel_z = (3.7 * time).sin();
-    let spin = Matrix4::from_euler_angles(angel_x, angel_y, angel_z);
+    self.graph.transform_mut(self.cube).rotation =
+      UnitQuaternion::from_euler_angles(angel_x, angel_y, angel_z);
+  }
+
+  fn render(&self) -> Result<(), anyhow::Error> {
     check_error();
     self.program.set_used();
-    self.vao.bind();
     unsafe {
       // 绑定纹理到对应的纹理单元
       GL.active_texture(glow::TEXTURE0);
-      self.texture.get(0)?.bind();
-      self
-        .program
-        .upload_mat4("vp_proj", &self.camera.get_vp_mat(aspect));
-      self.program.upload_mat4("m_proj", &spin);
-      GL.draw_elements(glow::TRIANGLES, 36, glow::UNSIGNED_INT, 0);
+      self.texture.first().context("缺少纹理")?.bind();
     }
-    self.vao.unbind();
+    self.graph.render(&self.program)?;
     self.program.detach();
-    Some(())
+    Ok(())
   }
 
   fn get_camera(&mut self) -> &mut Camera {
@@ -225,4 +214,8 @@ impl Scene for Cube {
   fn get_name(&self) -> ArcStr {
     ArcStr::from("spinning cube")
   }
+
+  fn get_graph(&mut self) -> &mut SceneGraph {
+    &mut self.graph
+  }
 }
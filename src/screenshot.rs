@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use image::RgbaImage;
+
+// 截图保存目录，相对于工作目录
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// 截图的内容
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Capture {
+  // 仅离屏渲染的场景
+  Scene,
+  // 包含egui界面的窗口画面
+  WithUi,
+}
+
+/// 以时间戳命名保存截图，返回文件路径
+pub fn save(image: &RgbaImage, capture: Capture) -> Result<PathBuf, anyhow::Error> {
+  std::fs::create_dir_all(SCREENSHOT_DIR).context("截图目录创建失败")?;
+  let millis = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis())
+    .unwrap_or_default();
+  let suffix = match capture {
+    Capture::Scene => "scene",
+    Capture::WithUi => "ui",
+  };
+  let path = PathBuf::from(SCREENSHOT_DIR).join(format!("screenshot-{}-{}.png", millis, suffix));
+  image
+    .save(&path)
+    .with_context(|| format!("截图保存失败 {:?}", path))?;
+  Ok(path)
+}
@@ -6,9 +6,21 @@ use once_cell::sync::Lazy;
 static PREVIOUS_TIME: Lazy<RwLock<f32>> = Lazy::new(|| RwLock::new(0.0));
 static DELTA_TIME: Lazy<RwLock<f32>> = Lazy::new(|| RwLock::new(0.0));
 static START: Lazy<Instant> = Lazy::new(|| Instant::now());
+// 固定的当前时间，用于可复现的渲染
+static FIXED_NOW: Lazy<RwLock<Option<f32>>> = Lazy::new(|| RwLock::new(None));
+
+/// 固定get_now的返回值，传入None恢复为真实时间
+pub fn set_fixed(now: Option<f32>) {
+  let mut fixed = FIXED_NOW.write().unwrap();
+  if fixed.is_some() && now.is_none() {
+    // 避免恢复后的首帧出现异常的时间差
+    *PREVIOUS_TIME.write().unwrap() = START.elapsed().as_secs_f32();
+  }
+  *fixed = now;
+}
 
 pub fn update() {
-  let now = START.elapsed().as_secs_f32();
+  let now = get_now();
   let mut pervious = PREVIOUS_TIME.write().unwrap();
   let mut delta = DELTA_TIME.write().unwrap();
   *delta = now - *pervious;
@@ -18,5 +30,8 @@ pub fn get_delta() -> f32 {
   *DELTA_TIME.read().unwrap()
 }
 pub fn get_now() -> f32 {
-  START.elapsed().as_secs_f32()
+  match *FIXED_NOW.read().unwrap() {
+    Some(now) => now,
+    None => START.elapsed().as_secs_f32(),
+  }
 }